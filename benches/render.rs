@@ -0,0 +1,149 @@
+// Benchmarks del raycaster: rayo individual, solo paredes, escena completa y descarte de sprites.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use proyecto1_raycasting::game::{Game, Player};
+use proyecto1_raycasting::level::{get_level, Level};
+use proyecto1_raycasting::raycaster::{cast_ray, render_scene, render_walls, DepthBuffer, RenderOptions};
+use proyecto1_raycasting::sprites::{Sprite, SpriteKind};
+
+const W: i32 = 640;
+const H: i32 = 400;
+
+// Poses de prueba: spawn mirando al oeste, centro del mapa y una diagonal
+fn poses(level: &Level) -> Vec<(&'static str, Player)> {
+    let (sx, sy) = level.spawn;
+    let spawn = Player::new(sx as f32 + 0.5, sy as f32 + 0.5);
+
+    // Primera celda libre desde el centro
+    let (mut cx, mut cy) = (level.w / 2, level.h / 2);
+    'search: for y in level.h / 2..level.h - 1 {
+        for x in level.w / 2..level.w - 1 {
+            if level.tile(x, y) == 0 {
+                (cx, cy) = (x, y);
+                break 'search;
+            }
+        }
+    }
+    let mut center = Player::new(cx as f32 + 0.5, cy as f32 + 0.5);
+    center.rotate(std::f32::consts::PI);
+
+    let mut diagonal = Player::new(sx as f32 + 0.5, sy as f32 + 0.5);
+    diagonal.rotate(std::f32::consts::PI * 1.25);
+
+    vec![("spawn", spawn), ("centro", center), ("diagonal", diagonal)]
+}
+
+fn bench_cast_ray(c: &mut Criterion) {
+    let level = get_level(2);
+    let (sx, sy) = level.spawn;
+    c.bench_function("cast_ray/nivel3", |b| {
+        b.iter(|| {
+            cast_ray(
+                black_box(&level),
+                black_box(sx as f32 + 0.5),
+                black_box(sy as f32 + 0.5),
+                black_box(0.8),
+                black_box(0.6),
+            )
+        })
+    });
+}
+
+fn bench_walls(c: &mut Criterion) {
+    let opts = RenderOptions::default();
+    let mut frame = vec![0u8; (W * H * 4) as usize];
+    let mut depth = DepthBuffer::new(W as usize);
+    for idx in 0..3 {
+        let level = get_level(idx);
+        for (name, player) in poses(&level) {
+            c.bench_function(&format!("paredes/nivel{}/{}", idx + 1, name), |b| {
+                b.iter(|| render_walls(&mut frame, W, H, &level, black_box(&player), &mut depth, &opts))
+            });
+        }
+    }
+}
+
+// Dirección de rayo recalculada por columna vs. incremental, en el nivel denso
+fn bench_incremental_rays(c: &mut Criterion) {
+    let mut frame = vec![0u8; (W * H * 4) as usize];
+    let mut depth = DepthBuffer::new(W as usize);
+    let level = get_level(2);
+    let mut group = c.benchmark_group("rayos_incrementales/nivel3");
+    for incremental in [false, true] {
+        let opts = RenderOptions { incremental_rays: incremental, ..RenderOptions::default() };
+        let name = if incremental { "incremental" } else { "directo" };
+        for (pose, player) in poses(&level) {
+            group.bench_function(format!("{}/{}", name, pose), |b| {
+                b.iter(|| render_walls(&mut frame, W, H, &level, black_box(&player), &mut depth, &opts))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_scene(c: &mut Criterion) {
+    let opts = RenderOptions::default();
+    let mut frame = vec![0u8; (W * H * 4) as usize];
+    let mut depth = DepthBuffer::new(W as usize);
+    for idx in 0..3 {
+        let level = get_level(idx);
+        let mut pellets = Vec::new();
+        let mut ghosts = Vec::new();
+        Game::build_sprites_for_level(&level, &mut pellets, &mut ghosts, false);
+        for (name, player) in poses(&level) {
+            c.bench_function(&format!("escena/nivel{}/{}", idx + 1, name), |b| {
+                b.iter(|| {
+                    render_scene(
+                        &mut frame,
+                        W,
+                        H,
+                        &level,
+                        black_box(&player),
+                        &pellets,
+                        &ghosts,
+                        &mut depth,
+                        &opts,
+                    )
+                })
+            });
+        }
+    }
+}
+
+// Sala abierta de 40x40 con una moneda por celda: la mayoría queda fuera del cuadro
+fn bench_sprite_culling(c: &mut Criterion) {
+    let mut frame = vec![0u8; (W * H * 4) as usize];
+    let mut depth = DepthBuffer::new(W as usize);
+    let wall = "#".repeat(40) + "\n";
+    let row = format!("#{}#\n", ".".repeat(38));
+    let spawn = format!("#{}P{}#\n", ".".repeat(19), ".".repeat(18));
+    let text = format!("{}{}{}{}{}", wall, row.repeat(19), spawn, row.repeat(18), wall);
+    let level = Level::from_reader(std::io::Cursor::new(text)).expect("nivel de benchmark");
+    let mut pellets = Vec::new();
+    for y in 1..level.h - 1 {
+        for x in 1..level.w - 1 {
+            pellets.push(Sprite::new(x as f32 + 0.5, y as f32 + 0.5, SpriteKind::Pellet));
+        }
+    }
+    let player = Player::new(20.5, 20.5);
+    let mut group = c.benchmark_group("descarte_sprites/sala_monedas");
+    for culling in [false, true] {
+        let opts = RenderOptions { sprite_culling: culling, ..RenderOptions::default() };
+        let name = if culling { "con_descarte" } else { "sin_descarte" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                render_scene(&mut frame, W, H, &level, black_box(&player), &pellets, &[], &mut depth, &opts)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_cast_ray,
+    bench_walls,
+    bench_incremental_rays,
+    bench_scene,
+    bench_sprite_culling
+);
+criterion_main!(benches);
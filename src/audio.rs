@@ -1,57 +1,282 @@
 use rodio::{Decoder, Sink, Source}; // OutputStream removido del import
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::BufReader;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Ducking: cuánto dura la música bajada tras un SFX importante y qué tan
+// rápido vuelve a su volumen (fracción por segundo)
+const DUCK_HOLD: f32 = 0.15;
+const DUCK_RECOVER_RATE: f32 = 6.0;
+
+// Música adaptativa: qué tan rápido sigue la mezcla a la tensión pedida (por segundo)
+const TENSION_FADE_RATE: f32 = 1.5;
+
+// Tema incluido con el juego; se usa si la música pedida falta o no se puede decodificar
+pub const DEFAULT_MUSIC_PATH: &str = "assets/music/default.ogg";
+
+// Volumen de los SFX (sin contar el eco)
+const SFX_VOLUME: f32 = 0.8;
+
+// SFX sonando a la vez por defecto; al pasarse se corta el más viejo
+pub const MAX_SFX_VOICES: usize = 8;
+
+// Eco de los SFX: `taps` copias del sonido, cada una `delay` segundos después
+// de la anterior y multiplicada otra vez por `decay`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EchoSettings {
+    pub delay: f32,
+    pub decay: f32,
+    pub taps: u32,
+}
+
+// Niveles con menos celdas que esto suenan secos
+const ECHO_MIN_CELLS: i32 = 200;
+
+impl EchoSettings {
+    // Según el tamaño del mapa: cuanto más grande, más largo y fuerte el eco
+    pub fn for_level_size(w: i32, h: i32) -> Option<Self> {
+        let cells = w * h;
+        if cells < ECHO_MIN_CELLS {
+            return None;
+        }
+        Some(Self {
+            delay: 0.06 + cells as f32 / 4000.0,
+            decay: (0.3 + cells as f32 / 3000.0).min(0.6),
+            taps: 2,
+        })
+    }
+}
+
+// Resultado del último play_music_loop (la pantalla de opciones lo muestra)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MusicStatus {
+    Playing,
+    Fallback, // sonando el tema por defecto en lugar del pedido
+    Missing,  // no hay archivo de música (ni el pedido ni el tema por defecto)
+    Failed,   // había archivo pero no se pudo decodificar ni usar el tema por defecto
+    NoDevice, // sin salida de audio
+}
+
+// Por qué no se pudo preparar una pista
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MusicError {
+    Missing,  // el archivo no existe o no se puede abrir
+    Unusable, // existe pero no se decodifica (o no hay sink)
+}
 
 pub struct AudioManager {
     // Mantenemos los tipos plenamente calificados para evitar imports extra
     _stream: Option<rodio::OutputStream>,
     stream_handle: Option<rodio::OutputStreamHandle>,
     music_sink: Arc<Mutex<Option<Sink>>>,
+    // Capa intensa de la música adaptativa; suena en paralelo a music_sink
+    intense_sink: Arc<Mutex<Option<Sink>>>,
+    pub tension: f32, // 0 = solo la capa tranquila, 1 = solo la intensa (lo fija el juego)
+    tension_mix: f32, // tensión suavizada que se aplica a los volúmenes
+    pub duck_amount: f32, // 0 = sin ducking, 1 = la música se silencia
+    duck_timer: f32,
+    music_volume: f32,
+    pub music_status: MusicStatus,
+    pub echo: Option<EchoSettings>, // None = SFX secos (por defecto)
+    // SFX en curso, del más viejo al más nuevo; los terminados se quitan en update
+    sfx_sinks: VecDeque<Sink>,
+    pub max_sfx_voices: usize,
+}
+
+impl Default for AudioManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AudioManager {
     pub fn new() -> Self {
-        let stream = rodio::OutputStream::try_default().ok();
+        // Sin dispositivo de audio el juego sigue, solo que en silencio
+        let stream = match rodio::OutputStream::try_default() {
+            Ok(s) => Some(s),
+            Err(e) => {
+                eprintln!("Aviso: audio deshabilitado ({})", e);
+                None
+            }
+        };
         let handle = stream.as_ref().map(|s| s.1.clone());
+        let handle_ok = handle.is_some();
         Self {
             _stream: stream.map(|s| s.0),
             stream_handle: handle,
             music_sink: Arc::new(Mutex::new(None)),
+            intense_sink: Arc::new(Mutex::new(None)),
+            tension: 0.0,
+            tension_mix: 0.0,
+            duck_amount: 0.6,
+            duck_timer: 0.0,
+            music_volume: 1.0,
+            music_status: if handle_ok { MusicStatus::Playing } else { MusicStatus::NoDevice },
+            echo: None,
+            sfx_sinks: VecDeque::new(),
+            max_sfx_voices: MAX_SFX_VOICES,
         }
     }
 
-    pub fn play_music_loop(&self, path: &str) {
-        if let Some(handle) = &self.stream_handle {
-            if let Ok(file) = File::open(path) {
-                let sink = Sink::try_new(handle).ok();
-                if let Some(sink) = sink {
-                    let source = Decoder::new(BufReader::new(file)).unwrap();
-                    sink.append(source.repeat_infinite());
-                    sink.play();
-                    if let Ok(mut s) = self.music_sink.lock() {
-                        if let Some(old) = s.take() {
-                            old.stop();
-                        }
-                        *s = Some(sink);
+    // Llamar una vez por frame: baja la música mientras dura el ducking y
+    // luego la devuelve suavemente a su volumen normal.
+    pub fn update(&mut self, dt: f32) {
+        self.sfx_sinks.retain(|s| !s.empty());
+        let target = if self.duck_timer > 0.0 {
+            self.duck_timer -= dt;
+            1.0 - self.duck_amount.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if target < self.music_volume {
+            self.music_volume = target;
+        } else {
+            self.music_volume = (self.music_volume + DUCK_RECOVER_RATE * dt).min(target);
+        }
+
+        // Fundido de potencia constante entre las dos capas; sin capa intensa la
+        // música suena entera
+        let step = TENSION_FADE_RATE * dt;
+        self.tension_mix += (self.tension.clamp(0.0, 1.0) - self.tension_mix).clamp(-step, step);
+        let angle = self.tension_mix * std::f32::consts::FRAC_PI_2;
+        let mut calm = 1.0;
+        if let Ok(s) = self.intense_sink.lock() {
+            if let Some(sink) = s.as_ref() {
+                sink.set_volume(self.music_volume * angle.sin());
+                calm = angle.cos();
+            }
+        }
+        if let Ok(s) = self.music_sink.lock() {
+            if let Some(sink) = s.as_ref() {
+                sink.set_volume(self.music_volume * calm);
+            }
+        }
+    }
+
+    // SFX que no debe taparse con la música (golpe, victoria, game over)
+    pub fn play_sfx_ducked(&mut self, path: &str) {
+        self.play_sfx(path);
+        self.duck_timer = DUCK_HOLD;
+    }
+
+    // Música en loop. Si el archivo falta o su formato no se puede decodificar
+    // se prueba con el tema por defecto; si tampoco anda, el juego sigue en silencio.
+    pub fn play_music_loop(&mut self, path: &str) -> MusicStatus {
+        self.stop_intense_layer();
+        self.music_status = if self.stream_handle.is_none() {
+            MusicStatus::NoDevice
+        } else {
+            match self.try_music(path) {
+                Ok(()) => MusicStatus::Playing,
+                Err(first) => {
+                    let fallback =
+                        if path == DEFAULT_MUSIC_PATH { Err(first) } else { self.try_music(DEFAULT_MUSIC_PATH) };
+                    match fallback {
+                        Ok(()) => MusicStatus::Fallback,
+                        // Sin ningún archivo no es un error: el juego puede ir sin música
+                        Err(MusicError::Missing) if first == MusicError::Missing => MusicStatus::Missing,
+                        Err(_) => MusicStatus::Failed,
                     }
                 }
-            } else {
-                // Silencioso si falta archivo
+            }
+        };
+        self.music_status
+    }
+
+    // Música adaptativa: dos pistas de igual duración que suenan sincronizadas y
+    // se mezclan según `tension`. Si la capa intensa falta (o la tranquila no es
+    // la pedida) queda solo la música normal.
+    pub fn play_music_layers(&mut self, calm: &str, intense: &str) -> MusicStatus {
+        let layer = self.build_music_sink(intense).ok();
+        let status = self.play_music_loop(calm);
+        if let (Some(sink), MusicStatus::Playing) = (layer, status) {
+            sink.set_volume(0.0);
+            sink.play();
+            if let Ok(mut s) = self.intense_sink.lock() {
+                *s = Some(sink);
+            }
+        }
+        self.tension_mix = 0.0;
+        status
+    }
+
+    fn stop_intense_layer(&mut self) {
+        if let Ok(mut s) = self.intense_sink.lock() {
+            if let Some(old) = s.take() {
+                old.stop();
             }
         }
     }
 
-    pub fn play_sfx(&self, path: &str) {
+    // Sink en pausa con `path` en loop; None si no se pudo abrir o decodificar
+    fn build_music_sink(&self, path: &str) -> Result<Sink, MusicError> {
+        let handle = self.stream_handle.as_ref().ok_or(MusicError::Unusable)?;
+        let file = File::open(path).map_err(|_| MusicError::Missing)?;
+        let source = match Decoder::new(BufReader::new(file)) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Aviso: no se pudo decodificar {} ({})", path, e);
+                return Err(MusicError::Unusable);
+            }
+        };
+        let sink = Sink::try_new(handle).map_err(|_| MusicError::Unusable)?;
+        sink.pause();
+        sink.append(source.repeat_infinite());
+        Ok(sink)
+    }
+
+    // Reemplaza la música actual por `path`; distingue archivo ausente de archivo roto
+    fn try_music(&self, path: &str) -> Result<(), MusicError> {
+        let sink = self.build_music_sink(path)?;
+        sink.play();
+        if let Ok(mut s) = self.music_sink.lock() {
+            if let Some(old) = s.take() {
+                old.stop();
+            }
+            *s = Some(sink);
+        }
+        Ok(())
+    }
+
+    // Cada SFX va en su propio sink; si ya hay max_sfx_voices sonando se corta
+    // el más viejo en lugar de abrir voces sin límite
+    pub fn play_sfx(&mut self, path: &str) {
         if let Some(handle) = &self.stream_handle {
             if let Ok(file) = File::open(path) {
                 if let Ok(dec) = Decoder::new(BufReader::new(file)) {
                     if let Ok(sink) = Sink::try_new(handle) {
-                        sink.append(dec.amplify(0.8));
-                        sink.detach();
+                        match self.echo {
+                            Some(echo) => sink.append(with_echo(dec, echo)),
+                            None => sink.append(dec.amplify(SFX_VOLUME)),
+                        }
+                        self.sfx_sinks.retain(|s| !s.empty());
+                        while self.sfx_sinks.len() >= self.max_sfx_voices.max(1) {
+                            if let Some(old) = self.sfx_sinks.pop_front() {
+                                old.stop();
+                            }
+                        }
+                        self.sfx_sinks.push_back(sink);
                     }
                 }
             }
         }
     }
+}
+
+// Mezcla el sonido original con sus copias retrasadas y atenuadas. El
+// decodificador se guarda en memoria (buffered) para poder reproducirlo varias veces.
+fn with_echo(dec: Decoder<BufReader<File>>, echo: EchoSettings) -> impl Source<Item = f32> + Send {
+    let (channels, rate) = (dec.channels(), dec.sample_rate());
+    let (controller, mixer) = rodio::dynamic_mixer::mixer::<f32>(channels, rate);
+    let sound = dec.buffered();
+    controller.add(sound.clone().convert_samples().amplify(SFX_VOLUME));
+    let mut gain = SFX_VOLUME;
+    for tap in 1..=echo.taps {
+        gain *= echo.decay;
+        let delay = Duration::from_secs_f32(echo.delay * tap as f32);
+        controller.add(sound.clone().convert_samples().delay(delay).amplify(gain));
+    }
+    mixer
 }
\ No newline at end of file
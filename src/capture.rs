@@ -0,0 +1,61 @@
+use anyhow::Context;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::thread;
+
+pub const CAPTURE_DIR: &str = "captures";
+// Guardar uno de cada N cuadros (1 = todos)
+pub const CAPTURE_EVERY: u32 = 1;
+// Cuadros en espera de ser escritos; si se llena se descartan en vez de frenar el juego
+const QUEUE_LEN: usize = 8;
+
+// Graba una secuencia de PNG numerados (frame0000.png, ...) para armar un GIF o
+// video aparte. La codificación y escritura se hacen en un hilo propio.
+pub struct FrameRecorder {
+    tx: SyncSender<(u32, Vec<u8>)>,
+    next_index: u32,
+    dropped: u32,
+}
+
+impl FrameRecorder {
+    pub fn new(dir: &str, w: u32, h: u32) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("No se pudo crear la carpeta {}", dir))?;
+        let dir = PathBuf::from(dir);
+        let (tx, rx) = sync_channel::<(u32, Vec<u8>)>(QUEUE_LEN);
+        thread::spawn(move || {
+            for (index, rgba) in rx {
+                let path = dir.join(format!("frame{:04}.png", index));
+                if let Err(e) = write_png(&path, w, h, &rgba) {
+                    eprintln!("Aviso: no se pudo guardar {} ({:#})", path.display(), e);
+                }
+            }
+        });
+        Ok(Self { tx, next_index: 0, dropped: 0 })
+    }
+
+    // Copia el cuadro y lo encola; no bloquea si el disco va atrasado
+    pub fn push(&mut self, frame: &[u8]) {
+        match self.tx.try_send((self.next_index, frame.to_vec())) {
+            Ok(()) => self.next_index += 1,
+            Err(TrySendError::Full(_)) => {
+                if self.dropped.is_multiple_of(60) {
+                    eprintln!("Aviso: el disco no alcanza a guardar la grabación, se descartan cuadros");
+                }
+                self.dropped += 1;
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+fn write_png(path: &Path, w: u32, h: u32, rgba: &[u8]) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), w, h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// Post-proceso "CRT": líneas de barrido, viñeta y un leve corrimiento de los
+// canales rojo y azul (aberración cromática). Trabaja sobre el cuadro ya
+// dibujado (escena + HUD) y lo reescribe en el lugar.
+
+// Con intensidad 1: cuánto se oscurecen las filas impares, cuánto las esquinas
+// y cuántos píxeles se corren los canales rojo/azul
+const SCANLINE_DARKEN: f32 = 0.35;
+const VIGNETTE_DARKEN: f32 = 0.45;
+const MAX_CHANNEL_OFFSET: f32 = 2.0;
+
+// intensity 0 = sin efecto, 1 = efecto completo
+pub fn apply_crt(frame: &mut [u8], w: i32, h: i32, intensity: f32) {
+    let intensity = intensity.clamp(0.0, 1.0);
+    if intensity <= 0.0 || w <= 0 || h <= 0 {
+        return;
+    }
+    // La viñeta es separable: factor por columna por factor por fila
+    let vignette = |i: i32, n: i32| {
+        let t = 2.0 * (i as f32 + 0.5) / n as f32 - 1.0;
+        1.0 - VIGNETTE_DARKEN * intensity * t * t * 0.5
+    };
+    let col_factor: Vec<f32> = (0..w).map(|x| vignette(x, w)).collect();
+    let offset = (MAX_CHANNEL_OFFSET * intensity).round() as i32;
+    let stride = (w * 4) as usize;
+    let mut row = vec![0u8; stride];
+
+    for y in 0..h {
+        let start = y as usize * stride;
+        let line = &mut frame[start..start + stride];
+        row.copy_from_slice(line);
+        let scan = if y % 2 == 1 { 1.0 - SCANLINE_DARKEN * intensity } else { 1.0 };
+        let row_factor = scan * vignette(y, h);
+        for x in 0..w {
+            let f = row_factor * col_factor[x as usize];
+            let px = x as usize * 4;
+            let red = row[(x + offset).min(w - 1) as usize * 4];
+            let blue = row[(x - offset).max(0) as usize * 4 + 2];
+            line[px] = (red as f32 * f) as u8;
+            line[px + 1] = (row[px + 1] as f32 * f) as u8;
+            line[px + 2] = (blue as f32 * f) as u8;
+        }
+    }
+}
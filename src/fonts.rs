@@ -12,8 +12,45 @@ pub fn draw_text_small(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, text: &
     }
 }
 
-fn draw_char_small(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, ch: char, color: [u8;4]) {
-    let pattern = match ch {
+// Igual que draw_text_small pero cada pixel del glifo ocupa scale x scale
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_scaled(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, text: &str, color: [u8;4], scale: i32) {
+    let scale = scale.max(1);
+    let mut cx = x;
+    for ch in text.chars() {
+        if ch == '\n' {
+            cx = x;
+            continue;
+        }
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..5 {
+                if (bits >> (4 - col)) & 1 == 1 {
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            let px = cx + col * scale + sx;
+                            let py = y + row as i32 * scale + sy;
+                            if px >= 0 && py >= 0 && px < w && py < h {
+                                let idx = ((py * w + px) * 4) as usize;
+                                frame[idx..idx + 4].copy_from_slice(&color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        cx += 6 * scale;
+    }
+}
+
+// Ancho y alto en píxeles de una línea de texto dibujada con draw_text_scaled
+pub fn text_size(text: &str, scale: i32) -> (i32, i32) {
+    let scale = scale.max(1);
+    let chars = text.chars().count() as i32;
+    ((chars * 6 - 1).max(0) * scale, 7 * scale)
+}
+
+fn glyph(ch: char) -> [u8; 7] {
+    match ch {
         'A'..='Z' => FONT[(ch as u8 - b'A') as usize + 10],
         'a'..='z' => FONT[(ch as u8 - b'a') as usize + 10],
         '0'..='9' => FONT[(ch as u8 - b'0') as usize],
@@ -21,13 +58,17 @@ fn draw_char_small(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, ch: char, c
         '.' => FONT[37],
         '-' => FONT[38],
         '!' => FONT[39],
+        '%' => FONT[40],
         ' ' => EMPTY,
         _ => EMPTY,
-    };
-    for (row, bits) in pattern.iter().enumerate() {
+    }
+}
+
+fn draw_char_small(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, ch: char, color: [u8;4]) {
+    for (row, bits) in glyph(ch).iter().enumerate() {
         for col in 0..5 {
             if (bits >> (4 - col)) & 1 == 1 {
-                let px = x + col as i32;
+                let px = x + col;
                 let py = y + row as i32;
                 if px >= 0 && py >= 0 && px < w && py < h {
                     let idx = ((py * w + px) * 4) as usize;
@@ -41,7 +82,7 @@ fn draw_char_small(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, ch: char, c
 const EMPTY: [u8; 7] = [0; 7];
 
 // 0-9, a-z compactos 5x7
-const FONT: [[u8; 7]; 40] = [
+const FONT: [[u8; 7]; 41] = [
     // 0-9
     [0b01110,0b10001,0b10011,0b10101,0b11001,0b10001,0b01110], // 0
     [0b00100,0b01100,0b00100,0b00100,0b00100,0b00100,0b01110], // 1
@@ -85,4 +126,5 @@ const FONT: [[u8; 7]; 40] = [
     [0b00000,0b00000,0b00000,0b00000,0b00000,0b00110,0b00110], // .
     [0b00000,0b00000,0b00000,0b11111,0b00000,0b00000,0b00000], // -
     [0b00100,0b00100,0b00100,0b00100,0b00000,0b00000,0b00100], // !
+    [0b11001,0b11010,0b00010,0b00100,0b01000,0b01011,0b10011], // %
 ];
\ No newline at end of file
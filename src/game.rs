@@ -1,105 +1,652 @@
-use crate::audio::AudioManager;
-use crate::fonts::draw_text_small;
-use crate::level::{get_level, Level};
-use crate::raycaster::{render_scene, DepthBuffer};
-use crate::sprites::{Sprite, SpriteKind};
+use crate::audio::{AudioManager, EchoSettings, MusicStatus};
+use crate::crt::apply_crt;
+use crate::fonts::{draw_text_scaled, draw_text_small, text_size};
+use crate::input::InputState;
+use crate::level::{
+    get_level, glass_tint, is_interactable, wall_frames, Level, Rooms, CUSTOM_LEVEL, LEVEL_COUNT,
+    TUTORIAL_LEVEL,
+};
+use crate::particles::{draw_particles, spawn_burst, update_particles, Particle};
+use crate::palette::WallPalette;
+use crate::raycaster::{
+    cast_ray, cast_ray_through, downsample_2x, draw_translucent_layers, render_scene_behind_glass,
+    DepthBuffer, RenderOptions, MISS_TILE, PEEK_DISTANCE,
+};
+use crate::settings::{ControlScheme, Settings, MAX_LIVES};
+use crate::sprites::{Ghost, GhostState, Sprite, SpriteKind, GHOST_NAMES};
+use crate::theme::{ghost_color, rgba, wall_color, Theme, THEME_PATH};
 use rand::Rng;
 use winit::event::VirtualKeyCode;
 
+// Cómo se ve el juego: en primera persona (raycaster) o desde arriba (V)
 #[derive(Copy, Clone, PartialEq, Eq)]
+enum ViewMode {
+    FirstPerson,
+    TopDown,
+}
+
+// Vista desde arriba: píxeles por celda (antes de ui_scale)
+const TOP_DOWN_CELL_PX: i32 = 24;
+
+#[derive(Clone, PartialEq, Eq)]
 enum Mode {
     Menu,
+    Settings,
     Playing,
     Paused,
+    WinSequence, // festejo corto (cámara girando) antes de la pantalla de victoria
     Win,
     GameOver,
+    LevelPreview, // mapa completo antes de empezar; Enter arranca
+    Error(String), // no se pudo cargar el nivel pedido: mensaje y cómo arreglarlo
+}
+
+// Festejo al ganar: duración, velocidad de giro (rad/s) y cuánto se aleja la
+// cámara para orbitar alrededor del jugador
+const WIN_SEQUENCE_TIME: f32 = 2.0;
+const WIN_ORBIT_SPEED: f32 = 2.0;
+const WIN_ORBIT_RADIUS: f32 = 1.0;
+
+// Longitud del plano de cámara = tan(FOV/2), ~66° de FOV
+pub const PLANE_LEN: f32 = 0.66;
+
+// Puntos base por moneda
+const PELLET_POINTS: u32 = 10;
+const BIG_DOT_POINTS: u32 = 50;
+// Distancia mínima (en celdas, Manhattan) entre dos monedas grandes
+const BIG_DOT_SPACING: f32 = 8.0;
+// Ventana para encadenar monedas en combo y multiplicador máximo
+const COMBO_WINDOW: f32 = 1.5;
+const MAX_COMBO: u32 = 5;
+// Intervalo mínimo entre sonidos de pellet: comer varios seguidos suena una sola vez
+const PELLET_SFX_INTERVAL: f32 = 0.06;
+// Y entre golpes contra la pared, para que empujar una pared no repita el sonido
+const BUMP_SFX_INTERVAL: f32 = 0.4;
+// Vida extra cada tantos puntos
+const EXTRA_LIFE_EVERY: u32 = 2000;
+// Alcance del clic para "usar" lo que está frente a la mira
+const USE_RANGE: f32 = 1.5;
+// Power pellet: duración del susto, puntos por fantasma comido,
+// velocidad de los ojos al volver a casa y espera antes de revivir
+const FRIGHTENED_TIME: f32 = 7.0;
+const GHOST_EAT_POINTS: u32 = 200;
+const EYES_SPEED: f32 = 4.0;
+const GHOST_RESPAWN_DELAY: f32 = 3.0;
+// Partículas del estallido al comer un fantasma (una moneda suelta 12)
+const GHOST_BURST_PARTICLES: usize = 24;
+// Separación buscada entre fantasmas al aparecer (celdas) e intentos antes de relajarla
+const GHOST_SPAWN_SPACING: f32 = 6.0;
+const GHOST_SPAWN_TRIES: usize = 50;
+// Variación de velocidad entre fantasmas (±15%) para que no persigan en bloque
+const GHOST_SPEED_SPREAD: f32 = 0.15;
+// Espera antes de guardar las opciones tras un cambio (agrupa cambios seguidos)
+const SETTINGS_SAVE_DELAY: f32 = 0.5;
+// Visión limitada: medio ángulo del cono (rad), alcance en celdas y cuánto
+// siguen persiguiendo después de perder de vista al jugador
+const GHOST_VISION_HALF_ANGLE: f32 = 0.87; // ~50°
+const GHOST_VISION_RANGE: f32 = 8.0;
+const GHOST_ALERT_TIME: f32 = 2.0;
+// Asomarse: rapidez con la que la cámara va y vuelve (fracción por segundo) y
+// radio libre que tiene que quedar alrededor de la cámara corrida
+const LEAN_SPEED: f32 = 10.0;
+const LEAN_CLEARANCE: f32 = 0.1;
+// Piso de lava: daño por segundo encima (una vida al llegar a 1), cuánto se
+// recupera por segundo fuera, y respiro tras perder una vida para poder salir
+const HAZARD_DAMAGE_RATE: f32 = 0.8;
+const HAZARD_RECOVER_RATE: f32 = 0.5;
+const HAZARD_COOLDOWN: f32 = 1.5;
+// Choques suaves (opción): aturdimiento y empuje inicial (celdas/s) que se frena
+// con BUMP_DRAG por segundo; BUMP_LIMIT choques con menos de BUMP_MEMORY segundos
+// entre uno y otro sí quitan una vida
+const BUMP_STUN_TIME: f32 = 0.6;
+const BUMP_SPEED: f32 = 4.0;
+const BUMP_DRAG: f32 = 6.0;
+const BUMP_LIMIT: u32 = 3;
+const BUMP_MEMORY: f32 = 4.0;
+// Debug: un rayo dibujado en el minimapa cada tantas columnas
+const DEBUG_RAY_EVERY: usize = 16;
+// Cristal de congelamiento: segundos que los fantasmas quedan quietos
+const FREEZE_TIME: f32 = 5.0;
+// Radar (Espacio): alcance en celdas, duración del efecto, espera entre usos y usos por nivel
+const PING_RADIUS: f32 = 8.0;
+const PING_DURATION: f32 = 1.0;
+const PING_COOLDOWN: f32 = 6.0;
+const PING_CHARGES: u32 = 3;
+// Agresividad: los fantasmas aceleran un 10% cada 30 s que dura el nivel, hasta +50%
+const AGGRESSION_INTERVAL: f32 = 30.0;
+const AGGRESSION_STEP: f32 = 0.10;
+const AGGRESSION_MAX_LEVEL: u32 = 5;
+// Estampida: al quedar pocas monedas los fantasmas van directo al jugador un rato
+const RUSH_PELLETS: usize = 5;
+const RUSH_DURATION: f32 = 6.0;
+
+// Demo del menú: tras unos segundos sin tocar nada la cámara recorre un nivel sola
+const ATTRACT_IDLE_TIME: f32 = 6.0;
+const ATTRACT_SPEED: f32 = 0.5; // fracción de la velocidad normal del jugador
+
+// Alto en píxeles de cada fila del menú de opciones
+const SETTINGS_ROW_H: i32 = 15;
+
+// Alto de referencia del HUD: las posiciones y tamaños fijos están pensados
+// para 400 px y se multiplican por ui_scale en resoluciones internas mayores
+const UI_REFERENCE_HEIGHT: i32 = 400;
+
+// Factor entero (la fuente bitmap no escala bien con fracciones), mínimo 1
+pub fn ui_scale(h: i32) -> i32 {
+    ((h + UI_REFERENCE_HEIGHT / 2) / UI_REFERENCE_HEIGHT).max(1)
 }
 
+// Ancho del panel de leyenda y contadores bajo el minimapa
+const MINIMAP_PANEL_W: i32 = 84;
+
+// Pasos del tutorial en orden; cada uno se completa con la acción que pide.
+// El último queda en pantalla hasta ganar.
+const TUTORIAL_PROMPTS: [&str; 5] = [
+    "Presiona W para avanzar",
+    "Gira con el mouse o las flechas",
+    "Recoge una moneda",
+    "Presiona P para pausar",
+    "Recoge todas las monedas para terminar",
+];
+const TUTORIAL_PAUSE_STEP: usize = 3;
+
+// Duración de cada cuadro de la animación de los fantasmas
+const GHOST_FRAME_TIME: f32 = 0.3;
+
+// Radios de colisión contra paredes (medio lado de la caja de cada cuerpo)
+const PLAYER_RADIUS: f32 = 0.2;
+const GHOST_RADIUS: f32 = 0.25;
+
+// Agarre sobre hielo (jugador y fantasmas): fracción por segundo con la que la
+// velocidad alcanza a la deseada
+const ICE_GRIP: f32 = 1.5;
+
+// Aceleración del mouse: factor extra por píxel de movimiento en un evento, y tope
+const MOUSE_ACCEL_GAIN: f32 = 0.02;
+const MOUSE_ACCEL_MAX: f32 = 2.5;
+// Tope de la mirada vertical hacia arriba o abajo (radianes)
+const MAX_PITCH: f32 = 0.6;
+
+// Paso fijo de simulación (60 Hz) y máximo de tiempo a recuperar por frame
+pub const FIXED_DT: f32 = 1.0 / 60.0;
+const MAX_FRAME_DT: f32 = 0.25;
+
+// Constante de tiempo (segundos) del promedio exponencial de los FPS
+const FPS_SMOOTHING: f32 = 0.5;
+
+// Música adaptativa: distancia al fantasma más cercano con tensión máxima y nula
+const TENSION_NEAR: f32 = 2.0;
+const TENSION_FAR: f32 = 8.0;
+
+#[derive(Clone)]
 pub struct Player {
     pub x: f32,
     pub y: f32,
+    pub prev_x: f32, // posición del paso anterior (interpolación)
+    pub prev_y: f32,
     pub dir_x: f32,
     pub dir_y: f32,
     pub plane_x: f32,
     pub plane_y: f32,
+    pub vel_x: f32, // velocidad actual (solo importa sobre hielo)
+    pub vel_y: f32,
     pub move_speed: f32,
     pub rot_speed: f32,
 }
 
+impl Player {
+    // Jugador mirando al oeste
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            prev_x: x,
+            prev_y: y,
+            dir_x: -1.0,
+            dir_y: 0.0,
+            plane_x: 0.0,
+            plane_y: PLANE_LEN,
+            vel_x: 0.0,
+            vel_y: 0.0,
+            move_speed: 3.0,
+            rot_speed: 2.0,
+        }
+    }
+
+    // Orienta la cámara según un ángulo (radianes, 0 = este). Con PI queda
+    // igual que Player::new: dir = (-1, 0), plane = (0, PLANE_LEN)
+    pub fn face(&mut self, angle: f32) {
+        self.dir_x = angle.cos();
+        self.dir_y = angle.sin();
+        self.plane_x = self.dir_y * PLANE_LEN;
+        self.plane_y = -self.dir_x * PLANE_LEN;
+    }
+
+    pub fn rotate(&mut self, angle: f32) {
+        let old_dir_x = self.dir_x;
+        self.dir_x = self.dir_x * angle.cos() - self.dir_y * angle.sin();
+        self.dir_y = old_dir_x * angle.sin() + self.dir_y * angle.cos();
+
+        let old_plane_x = self.plane_x;
+        self.plane_x = self.plane_x * angle.cos() - self.plane_y * angle.sin();
+        self.plane_y = old_plane_x * angle.sin() + self.plane_y * angle.cos();
+
+        // Corregir el error de punto flotante en cada giro para que el FOV
+        // (|plane| / |dir|) no derive en sesiones largas
+        self.orthonormalize();
+    }
+
+    // dir unitario y plane perpendicular a dir con longitud PLANE_LEN (= tan(FOV/2)).
+    // Se conserva el lado hacia el que apuntaba plane.
+    fn orthonormalize(&mut self) {
+        let len = (self.dir_x * self.dir_x + self.dir_y * self.dir_y).sqrt();
+        if len < 1e-6 {
+            self.dir_x = -1.0;
+            self.dir_y = 0.0;
+        } else {
+            self.dir_x /= len;
+            self.dir_y /= len;
+        }
+        let side = if self.dir_x * self.plane_y - self.dir_y * self.plane_x >= 0.0 { 1.0 } else { -1.0 };
+        self.plane_x = -self.dir_y * PLANE_LEN * side;
+        self.plane_y = self.dir_x * PLANE_LEN * side;
+    }
+}
+
+// Parámetros de la IA de fantasmas, ajustables en vivo en modo debug
+#[derive(Copy, Clone)]
+pub struct GhostTuning {
+    pub speed: f32,
+    pub scatter_r: f32, // offset alrededor del jugador
+    pub sep_r: f32,     // separación entre fantasmas
+}
+
+impl Default for GhostTuning {
+    fn default() -> Self {
+        Self {
+            speed: 1.35,
+            scatter_r: 1.6,
+            sep_r: 0.9,
+        }
+    }
+}
+
+// Resumen del último nivel ganado, para la pantalla de victoria
+struct LevelStats {
+    collected: usize,
+    total: usize,
+    time: f32,
+    ghosts_eaten: u32,
+    score: u32,
+    new_best_time: bool,
+    new_best_score: bool,
+}
+
+// Última vez que el jugador vio a un fantasma (minimapa con memoria)
+#[derive(Copy, Clone)]
+struct GhostSighting {
+    x: f32,
+    y: f32,
+    time: f32, // self.time del avistamiento
+}
+
+// Segundos que tarda en borrarse del minimapa un fantasma que se dejó de ver
+const GHOST_MEMORY_FADE: f32 = 6.0;
+
+// Rastro en el minimapa: segundos que tarda en borrarse una celda pisada, su
+// color y cuánto tiñe el piso una celda recién pisada (0..1)
+const TRAIL_FADE: f32 = 30.0;
+const TRAIL_COLOR: [u8; 3] = [90, 160, 90];
+const TRAIL_STRENGTH: f32 = 0.7;
+
+// Movimiento en grilla (opción): el jugador va de centro en centro de celda
+// mirando a uno de los cuatro puntos cardinales y solo gira en las intersecciones
+#[derive(Default)]
+struct GridMove {
+    active: bool,               // ya se alineó con la grilla
+    facing: usize,              // índice en CARDINALS
+    target: Option<(i32, i32)>, // celda hacia la que avanza; None = quieto en un centro
+    queued_turn: Option<usize>, // giro pendiente en cuartos de vuelta (1, 2 o 3)
+    view_angle: f32,            // ángulo actual de la cámara (gira suave hacia facing)
+}
+
+// Este, sur, oeste, norte (ángulo = índice * 90°)
+const CARDINALS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+// Velocidad del giro de cámara en modo grilla (rad/s)
+const GRID_TURN_SPEED: f32 = 9.0;
+
 pub struct Game {
     mode: Mode,
+    pub debug: bool, // --debug: overlay y teclas de ajuste
+    show_ghost_targets: bool, // debug: objetivos de la IA en el minimapa
+    show_rays: bool,          // debug: rayos del cuadro en el minimapa
+    debug_paused: bool,       // debug: simulación quieta (aparte de la pausa del juego)
+    wall_palette: WallPalette, // debug: colores de paredes alternativos (F8)
+    step_requested: bool,     // debug: avanzar un solo paso fijo en el próximo update
+    anim_clock: f32,          // reloj global de animación: corre siempre, también en menús
+    help_visible: bool,       // overlay de ayuda (H); pausa la simulación
+    show_legend: bool,        // leyenda de colores de fantasmas bajo el minimapa (L)
+    view_mode: ViewMode,      // primera persona o desde arriba (V)
+    menu_idle: f32,     // segundos en el menú sin teclas
+    attract: bool,      // demo con piloto automático detrás del menú
+    attract_level: usize,
+    attract_turn: f32,  // sentido de giro mientras esquiva una pared (+1 / -1)
+    grid: GridMove,
+    tutorial_step: Option<usize>, // paso actual si se está jugando el tutorial
+    pub recording: bool,      // F9: main guarda cada cuadro como PNG en captures/
+    pub capture_frame: u32,   // cuadros vistos desde que empezó la grabación
     pub level_index: usize,
     pub level: Level,
+    custom_level: Option<Level>, // nivel cargado con --level (índice CUSTOM_LEVEL)
     pub player: Player,
     pub pressed: [bool; 256],
-    pub fps: f32,
-    fps_acc: f32,
-    fps_count: u32,
+    pub fps: f32,     // suavizado (promedio exponencial), el que muestra el HUD
+    pub fps_raw: f32, // instantáneo (1 / dt del último cuadro), para depuración
+    accumulator: f32, // tiempo pendiente de simular en pasos fijos
     pub audio: AudioManager,
-    pub sprites: Vec<Sprite>,
+    pub pellets: Vec<Sprite>,
+    pub ghosts: Vec<Ghost>,
+    pub particles: Vec<Particle>,
     pub pellets_remaining: usize,
     pub depth: DepthBuffer,
-    mouse_sensitivity: f32,
+    ss_frame: Vec<u8>, // buffer al doble de resolución para el antialiasing
+    pub render_opts: RenderOptions,
+    control_scheme: ControlScheme,
+    pub settings: Settings,
+    pub theme: Theme, // tema cargado de assets/theme.json (paleta Normal)
+    rooms: Rooms, // habitaciones del nivel actual (para el minimapa)
+    settings_cursor: usize,
+    settings_path: Option<String>, // dónde guardar las opciones; None no las guarda
+    settings_save_in: Option<f32>, // segundos hasta escribir un cambio pendiente
+    mouse_pending: f32, // giro del mouse aún no aplicado (suavizado), en radianes
+    pitch: f32,         // mirada vertical en radianes (+ = arriba), entre -MAX_PITCH y MAX_PITCH
+    pub ghost_tuning: GhostTuning,
 
     // Vidas y estado
-    pub lives: i32,        // 3 vidas por nivel
+    pub lives: i32,        // vidas por nivel (Settings::starting_lives)
     invincible_time: f32,  // invulnerabilidad tras perder vida
+    stun_time: f32,        // aturdido tras un choque suave: sin control del movimiento
+    knockback: (f32, f32), // velocidad del empuje del último choque suave
+    bump_count: u32,       // choques suaves seguidos (se olvidan tras BUMP_MEMORY)
+    bump_memory: f32,      // segundos que quedan para que cuente el próximo choque
     time: f32,             // tiempo global (IA)
     death_anim_t: f32,     // animación de game over
+    win_seq_t: f32,        // tiempo transcurrido del festejo de victoria
+    spectating: bool,      // cámara libre tras el fade de game over
+    time_left: Option<f32>, // cuenta regresiva en modo contrarreloj
+    time_up: bool,          // el game over fue por tiempo
 
     // Contador total de monedas del nivel
     pub total_pellets: usize,
+    campaign_pellets: [usize; LEVEL_COUNT],   // monedas de cada nivel (se calcula una vez)
+    campaign_collected: [usize; LEVEL_COUNT], // mejor cantidad recogida en cada nivel
+    best_times: [Option<f32>; LEVEL_COUNT],   // mejor tiempo al ganar cada nivel
+    best_scores: [u32; LEVEL_COUNT],          // mejor puntaje al ganar cada nivel
+    ghosts_eaten: u32,                        // fantasmas comidos en el nivel actual
+    ghost_sightings: Vec<Option<GhostSighting>>, // por fantasma (mismo orden que ghosts)
+    trail: Vec<f32>, // rastro: self.time de la última pisada de cada celda (-inf = nunca)
+    rooms_entered: Vec<bool>, // habitaciones ya visitadas en este intento (ver update_room_entry)
+    last_stats: Option<LevelStats>,
+
+    // Puntaje y combo de monedas
+    pub score: u32,
+    combo_count: u32,
+    combo_timer: f32,
+    next_extra_life: u32,  // puntaje en el que se gana la próxima vida
+    extra_life_flash: f32, // aviso en el HUD tras ganar una vida
+    aggression_level: u32,  // escalones de velocidad ya aplicados a los fantasmas
+    aggression_flash: f32,  // aviso en el HUD al subir un escalón
+    view_reset_flash: f32,  // aviso en el HUD tras reiniciar la cámara con Inicio
+    freeze_time: f32,       // fantasmas congelados por el cristal (segundos restantes)
+    ping_time: f32,         // efecto del radar en curso (segundos restantes)
+    ping_cooldown: f32,
+    ping_charges: u32, // usos de radar que quedan en el nivel
+    rush_time: f32,    // estampida en curso (segundos restantes)
+    rush_done: bool,   // la estampida ocurre una sola vez por nivel
+    hazard_damage: f32,   // daño acumulado sobre lava (0..1)
+    hazard_cooldown: f32, // respiro tras quemarse
+    lean: f32,            // asomarse: -1 izquierda .. 1 derecha (solo mueve la cámara)
+    shake_time: f32,       // vibración de cámara restante (solo visual)
+    shake_duration: f32,
+    shake_intensity: f32, // amplitud inicial en píxeles
+    pellet_sfx_cooldown: f32,
+    bump_sfx_cooldown: f32,
 }
 
 impl Game {
     pub fn new(width: i32, _height: i32) -> anyhow::Result<Self> {
         let level_index = 0;
         let level = get_level(level_index);
+        let rooms = level.segment_rooms();
         let (px, py) = level.spawn;
 
-        let player = Player {
-            x: px as f32 + 0.5,
-            y: py as f32 + 0.5,
-            dir_x: -1.0,
-            dir_y: 0.0,
-            plane_x: 0.0,
-            plane_y: 0.66,
-            move_speed: 3.0,
-            rot_speed: 2.0,
-        };
+        let (sx, sy) = level.cell_center(px, py);
+        let player = Player::new(sx, sy);
 
         let audio = AudioManager::new();
-        let sprites = Self::build_sprites_for_level(&level);
-        let total_pellets = sprites.iter().filter(|s| s.kind == SpriteKind::Pellet).count();
+        let mut pellets = Vec::new();
+        let mut ghosts = Vec::new();
+        Self::build_sprites_for_level(&level, &mut pellets, &mut ghosts, false);
+        let total_pellets = pellets.len();
+        let campaign_pellets = Self::count_campaign_pellets();
         let pellets_remaining = total_pellets;
 
-        Ok(Self {
+        let mut game = Self {
             mode: Mode::Menu,
+            debug: false,
+            show_ghost_targets: false,
+            show_rays: false,
+            debug_paused: false,
+            wall_palette: WallPalette::Classic,
+            step_requested: false,
+            anim_clock: 0.0,
+            help_visible: false,
+            show_legend: false,
+            view_mode: ViewMode::FirstPerson,
+            menu_idle: 0.0,
+            attract: false,
+            attract_level: 0,
+            attract_turn: 1.0,
+            grid: GridMove::default(),
+            tutorial_step: None,
+            recording: false,
+            capture_frame: 0,
             level_index,
             level,
+            custom_level: None,
             player,
             pressed: [false; 256],
             fps: 0.0,
-            fps_acc: 0.0,
-            fps_count: 0,
+            fps_raw: 0.0,
+            accumulator: 0.0,
             audio,
-            sprites,
+            pellets,
+            ghosts,
+            particles: Vec::new(),
             pellets_remaining,
             depth: DepthBuffer::new(width as usize),
-            mouse_sensitivity: 0.0035,
+            ss_frame: Vec::new(),
+            render_opts: RenderOptions::default(),
+            control_scheme: ControlScheme::Tank,
+            settings: Settings::default(),
+            theme: Theme::load(THEME_PATH),
+            rooms,
+            settings_cursor: 0,
+            settings_path: None,
+            settings_save_in: None,
+            mouse_pending: 0.0,
+            pitch: 0.0,
+            ghost_tuning: GhostTuning::default(),
 
             lives: 3,
             invincible_time: 0.0,
+            stun_time: 0.0,
+            knockback: (0.0, 0.0),
+            bump_count: 0,
+            bump_memory: 0.0,
             time: 0.0,
             death_anim_t: 0.0,
+            win_seq_t: 0.0,
+            spectating: false,
+            time_left: None,
+            time_up: false,
 
             total_pellets,
+            campaign_pellets,
+            campaign_collected: [0; LEVEL_COUNT],
+            best_times: [None; LEVEL_COUNT],
+            best_scores: [0; LEVEL_COUNT],
+            ghosts_eaten: 0,
+            ghost_sightings: Vec::new(),
+            trail: Vec::new(),
+            rooms_entered: Vec::new(),
+            last_stats: None,
+
+            score: 0,
+            combo_count: 0,
+            combo_timer: 0.0,
+            next_extra_life: EXTRA_LIFE_EVERY,
+            extra_life_flash: 0.0,
+            aggression_level: 0,
+            aggression_flash: 0.0,
+            view_reset_flash: 0.0,
+            freeze_time: 0.0,
+            ping_time: 0.0,
+            ping_cooldown: 0.0,
+            ping_charges: PING_CHARGES,
+            rush_time: 0.0,
+            rush_done: false,
+            hazard_damage: 0.0,
+            hazard_cooldown: 0.0,
+            lean: 0.0,
+            shake_time: 0.0,
+            shake_duration: 0.0,
+            shake_intensity: 0.0,
+            pellet_sfx_cooldown: 0.0,
+            bump_sfx_cooldown: 0.0,
+        };
+        game.apply_settings();
+        Ok(game)
+    }
+
+    // Vuelca las opciones actuales al estado que las consume
+    fn apply_settings(&mut self) {
+        self.render_opts.column_step = self.settings.quality.column_step();
+        self.render_opts.shadow_intensity = self.settings.shadow_intensity;
+        self.render_opts.incremental_rays = self.settings.incremental_rays;
+        self.render_opts.max_ray_distance = self.settings.view_distance;
+        self.render_opts.depth_contrast = self.settings.depth_contrast;
+        self.render_opts.peek_distance = if self.settings.peek_walls { PEEK_DISTANCE } else { 0.0 };
+        self.control_scheme = self.settings.controls;
+        self.refresh_theme();
+        self.audio.duck_amount = self.settings.music_duck;
+        self.update_echo();
+        // Al volver a activar la grilla hay que realinearse
+        self.grid.active = false;
+    }
+
+    // Opciones guardadas en `path`; desde ahora cada cambio se vuelve a escribir ahí
+    pub fn load_settings(&mut self, path: &str) {
+        self.settings = Settings::load(path);
+        self.settings_path = Some(path.to_string());
+        self.apply_settings();
+    }
+
+    // Cambio desde el menú de opciones: se aplica en el acto y el guardado se
+    // demora un poco para escribir una sola vez si se cambian varias seguidas
+    fn change_setting(&mut self, delta: i32) {
+        self.settings.adjust(self.settings_cursor, delta);
+        self.apply_settings();
+        if self.settings_path.is_some() {
+            self.settings_save_in = Some(SETTINGS_SAVE_DELAY);
+        }
+    }
+
+    // Escribe ya mismo un cambio pendiente (también al salir). Si falla se avisa
+    // por consola y se sigue jugando; el próximo cambio lo vuelve a intentar.
+    pub fn save_settings(&mut self) {
+        if self.settings_save_in.take().is_none() {
+            return;
+        }
+        if let Some(path) = &self.settings_path {
+            if let Err(e) = self.settings.save(path) {
+                eprintln!("Aviso: no se pudieron guardar las opciones ({:#})", e);
+            }
+        }
+    }
+
+    // Tema de render: paleta de las opciones y, encima, la paleta de paredes de depuración
+    fn refresh_theme(&mut self) {
+        self.render_opts.theme = self.settings.palette.theme(&self.theme);
+        self.wall_palette.apply(&mut self.render_opts.theme);
+    }
+
+    // Eco de los SFX según el nivel actual, si está activado en opciones
+    fn update_echo(&mut self) {
+        self.audio.echo = if self.settings.sfx_echo {
+            EchoSettings::for_level_size(self.level.w, self.level.h)
+        } else {
+            None
+        };
+    }
+
+    // Monedas de cada nivel de la campaña, armando sus sprites sin empezar a jugar
+    fn count_campaign_pellets() -> [usize; LEVEL_COUNT] {
+        let mut pellets = Vec::new();
+        let mut ghosts = Vec::new();
+        std::array::from_fn(|i| {
+            Self::build_sprites_for_level(&get_level(i), &mut pellets, &mut ghosts, true);
+            pellets.len()
         })
     }
 
-    // Menos monedas: aprox 1 de cada 6 celdas vacías, determinista por coordenadas
-    fn build_sprites_for_level(level: &Level) -> Vec<Sprite> {
-        let mut sprites = Vec::new();
+    // Arma el resumen del nivel recién ganado y actualiza los mejores tiempos y
+    // puntajes (el tutorial no tiene récords)
+    fn record_level_stats(&mut self) {
+        let i = self.level_index;
+        let new_best_time = match self.best_times.get_mut(i) {
+            Some(best) if best.is_none_or(|t| self.time < t) => {
+                *best = Some(self.time);
+                true
+            }
+            _ => false,
+        };
+        let new_best_score = match self.best_scores.get_mut(i) {
+            Some(best) if self.score > *best => {
+                *best = self.score;
+                true
+            }
+            _ => false,
+        };
+        self.last_stats = Some(LevelStats {
+            collected: self.total_pellets.saturating_sub(self.pellets_remaining),
+            total: self.total_pellets,
+            time: self.time,
+            ghosts_eaten: self.ghosts_eaten,
+            score: self.score,
+            new_best_time,
+            new_best_score,
+        });
+    }
+
+    // (recogidas, total) sumando todos los niveles de la campaña
+    pub fn campaign_progress(&self) -> (usize, usize) {
+        (self.campaign_collected.iter().sum(), self.campaign_pellets.iter().sum())
+    }
+
+    // Menos monedas: aprox 1 de cada 6 celdas vacías, determinista por coordenadas.
+    // Reutiliza los Vec recibidos para no reasignar memoria en cada nivel.
+    // no_ghosts: modo práctica, el nivel queda sin fantasmas.
+    pub fn build_sprites_for_level(
+        level: &Level,
+        pellets: &mut Vec<Sprite>,
+        ghosts: &mut Vec<Ghost>,
+        no_ghosts: bool,
+    ) {
+        pellets.clear();
+        pellets.reserve((level.w * level.h) as usize / 6);
+        ghosts.clear();
+        ghosts.reserve(if no_ghosts { 0 } else { level.ghost_count });
 
         for y in 0..level.h {
             for x in 0..level.w {
@@ -108,38 +655,185 @@ impl Game {
                         continue;
                     }
                     if ((x + y * 3) % 6) == 0 {
-                        sprites.push(Sprite::new(x as f32 + 0.5, y as f32 + 0.5, SpriteKind::Pellet));
+                        let (fx, fy) = level.cell_center(x, y);
+                        pellets.push(Sprite::new(fx, fy, SpriteKind::Pellet));
+                    }
+                }
+            }
+        }
+
+        // Power pellets: la celda libre más cercana a cada esquina
+        let corners = [(1, 1), (level.w - 2, 1), (1, level.h - 2), (level.w - 2, level.h - 2)];
+        for (cx, cy) in corners {
+            let mut best: Option<(i32, i32, i32)> = None;
+            for y in 1..level.h - 1 {
+                for x in 1..level.w - 1 {
+                    if level.map[(y * level.w + x) as usize] != 0 || (x, y) == level.spawn {
+                        continue;
+                    }
+                    let d = (x - cx).abs() + (y - cy).abs();
+                    if best.is_none_or(|(_, _, bd)| d < bd) {
+                        best = Some((x, y, d));
+                    }
+                }
+            }
+            if let Some((x, y, _)) = best {
+                let (fx, fy) = level.cell_center(x, y);
+                // Reemplaza una moneda normal en la misma celda si la hay
+                pellets.retain(|p| p.x != fx || p.y != fy);
+                if !pellets.iter().any(|p| p.kind == SpriteKind::PowerPellet && p.x == fx && p.y == fy) {
+                    pellets.push(Sprite::new(fx, fy, SpriteKind::PowerPellet));
+                }
+            }
+        }
+
+        // Monedas grandes en los callejones sin salida (celdas libres con un solo vecino libre),
+        // separadas entre sí para que no se amontonen en mapas con muchos recovecos
+        for y in 1..level.h - 1 {
+            for x in 1..level.w - 1 {
+                if level.map[(y * level.w + x) as usize] != 0 || (x, y) == level.spawn {
+                    continue;
+                }
+                let open = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                    .iter()
+                    .filter(|(dx, dy)| level.tile(x + dx, y + dy) == 0)
+                    .count();
+                let (fx, fy) = level.cell_center(x, y);
+                let power_here = pellets.iter().any(|p| p.kind == SpriteKind::PowerPellet && p.x == fx && p.y == fy);
+                let crowded = pellets
+                    .iter()
+                    .any(|p| p.kind == SpriteKind::BigDot && (p.x - fx).abs() + (p.y - fy).abs() < BIG_DOT_SPACING);
+                if open == 1 && !power_here && !crowded {
+                    pellets.retain(|p| p.x != fx || p.y != fy);
+                    pellets.push(Sprite::new(fx, fy, SpriteKind::BigDot));
+                }
+            }
+        }
+
+        // Cristal de congelamiento: la celda libre más cercana al centro (solo con fantasmas)
+        if level.ghost_count > 0 {
+            let (cx, cy) = (level.w / 2, level.h / 2);
+            let mut best: Option<(i32, i32, i32)> = None;
+            for y in 1..level.h - 1 {
+                for x in 1..level.w - 1 {
+                    let (fx, fy) = level.cell_center(x, y);
+                    if level.map[(y * level.w + x) as usize] != 0
+                        || (x, y) == level.spawn
+                        || pellets.iter().any(|p| p.kind == SpriteKind::PowerPellet && p.x == fx && p.y == fy)
+                    {
+                        continue;
+                    }
+                    let d = (x - cx).abs() + (y - cy).abs();
+                    if best.is_none_or(|(_, _, bd)| d < bd) {
+                        best = Some((x, y, d));
                     }
                 }
             }
+            if let Some((x, y, _)) = best {
+                let (fx, fy) = level.cell_center(x, y);
+                pellets.retain(|p| p.x != fx || p.y != fy);
+                pellets.push(Sprite::new(fx, fy, SpriteKind::FreezePellet));
+            }
         }
 
         // Garantiza al menos 1 pellet por nivel
-        if !sprites.iter().any(|s| s.kind == SpriteKind::Pellet) {
+        if pellets.is_empty() {
             'outer: for y in 1..level.h - 1 {
                 for x in 1..level.w - 1 {
                     if level.map[(y * level.w + x) as usize] == 0 && (x, y) != level.spawn {
-                        sprites.push(Sprite::new(x as f32 + 0.5, y as f32 + 0.5, SpriteKind::Pellet));
+                        let (fx, fy) = level.cell_center(x, y);
+                        pellets.push(Sprite::new(fx, fy, SpriteKind::Pellet));
                         break 'outer;
                     }
                 }
             }
         }
 
-        // Fantasmas en posiciones aleatorias válidas
+        // Fantasmas: primero en las casas que define el nivel (las que caigan en
+        // pared se ignoran) y el resto en posiciones aleatorias válidas y separadas
+        let ghost_count = if no_ghosts { 0 } else { level.ghost_count };
+        for &(x, y) in level.ghost_spawns.iter().take(ghost_count) {
+            if level.tile(x, y) == 0 {
+                Self::spawn_ghost_at(level, ghosts, x, y);
+            }
+        }
         let mut rng = rand::thread_rng();
-        for _ in 0..level.ghost_count {
-            for _tries in 0..200 {
+        for _ in ghosts.len()..ghost_count {
+            Self::spawn_random_ghost(level, ghosts, &mut rng);
+        }
+
+        // Velocidades repartidas en forma pareja entre -SPREAD y +SPREAD: el
+        // promedio del grupo sigue siendo la velocidad de GhostTuning
+        let n = ghosts.len();
+        if n > 1 {
+            for (i, g) in ghosts.iter_mut().enumerate() {
+                g.speed = 1.0 + GHOST_SPEED_SPREAD * (2.0 * i as f32 / (n - 1) as f32 - 1.0);
+            }
+        }
+    }
+
+    // Celda libre al azar lejos de los fantasmas ya puestos. Si tras varios intentos
+    // no hay lugar con esa separación, se pide la mitad, y así hasta no pedir nada.
+    fn spawn_random_ghost(level: &Level, ghosts: &mut Vec<Ghost>, rng: &mut impl Rng) {
+        let mut spacing = GHOST_SPAWN_SPACING;
+        loop {
+            for _tries in 0..GHOST_SPAWN_TRIES {
                 let gx = rng.gen_range(1..(level.w - 1));
                 let gy = rng.gen_range(1..(level.h - 1));
-                if level.map[(gy * level.w + gx) as usize] == 0 {
-                    sprites.push(Sprite::new(gx as f32 + 0.5, gy as f32 + 0.5, SpriteKind::Ghost));
-                    break;
+                let (cx, cy) = level.cell_center(gx, gy);
+                let apart = ghosts
+                    .iter()
+                    .all(|g| (g.x - cx).powi(2) + (g.y - cy).powi(2) >= spacing * spacing);
+                if level.map[(gy * level.w + gx) as usize] == 0 && apart {
+                    Self::spawn_ghost_at(level, ghosts, gx, gy);
+                    return;
                 }
             }
+            if spacing == 0.0 {
+                return;
+            }
+            spacing = if spacing > 1.0 { spacing / 2.0 } else { 0.0 };
         }
+    }
 
-        sprites
+    // Fantasma en el centro de la celda (x, y), con el siguiente color de la lista
+    fn spawn_ghost_at(level: &Level, ghosts: &mut Vec<Ghost>, x: i32, y: i32) {
+        let (cx, cy) = level.cell_center(x, y);
+        let mut g = Ghost::new(cx, cy);
+        g.color_index = ghosts.len() % GHOST_NAMES.len();
+        ghosts.push(g);
+    }
+
+    // Teclas de ajuste de la IA (solo con --debug)
+    fn on_debug_key(&mut self, key: VirtualKeyCode) {
+        let t = &mut self.ghost_tuning;
+        match key {
+            VirtualKeyCode::LBracket => t.speed = (t.speed - 0.05).max(0.0),
+            VirtualKeyCode::RBracket => t.speed += 0.05,
+            VirtualKeyCode::Comma => t.scatter_r = (t.scatter_r - 0.1).max(0.0),
+            VirtualKeyCode::Period => t.scatter_r += 0.1,
+            VirtualKeyCode::Semicolon => t.sep_r = (t.sep_r - 0.1).max(0.1),
+            VirtualKeyCode::Apostrophe => t.sep_r += 0.1,
+            VirtualKeyCode::Minus => {
+                self.ghosts.pop();
+            }
+            VirtualKeyCode::Equals => {
+                Self::spawn_random_ghost(&self.level, &mut self.ghosts, &mut rand::thread_rng())
+            }
+            // Saltar de nivel al instante, con vuelta al primero/último
+            VirtualKeyCode::PageDown => self.start_level((self.level_index + 1) % LEVEL_COUNT),
+            VirtualKeyCode::PageUp => self.start_level((self.level_index + LEVEL_COUNT - 1) % LEVEL_COUNT),
+            VirtualKeyCode::F3 => self.render_opts.depth_view = !self.render_opts.depth_view,
+            VirtualKeyCode::F4 => self.show_ghost_targets = !self.show_ghost_targets,
+            VirtualKeyCode::F5 => self.show_rays = !self.show_rays,
+            VirtualKeyCode::F6 => self.debug_paused = !self.debug_paused,
+            VirtualKeyCode::F7 => self.step_requested = self.debug_paused,
+            VirtualKeyCode::F8 => {
+                self.wall_palette = self.wall_palette.next();
+                self.refresh_theme();
+            }
+            _ => {}
+        }
     }
 
     pub fn on_key(&mut self, key: VirtualKeyCode, pressed: bool) {
@@ -147,23 +841,84 @@ impl Game {
         if idx < self.pressed.len() {
             self.pressed[idx] = pressed;
         }
+        if pressed {
+            self.menu_idle = 0.0;
+            // Cualquier tecla corta la demo y vuelve al menú quieto
+            if self.attract {
+                self.attract = false;
+                return;
+            }
+        }
+
+        // Grabación de cuadros: disponible en cualquier pantalla
+        if pressed && key == VirtualKeyCode::F9 {
+            self.recording = !self.recording;
+            self.capture_frame = 0;
+            return;
+        }
+
+        // Con la ayuda abierta solo se atiende H para cerrarla
+        let help_mode = matches!(self.mode, Mode::Menu | Mode::Playing);
+        if pressed && help_mode && key == VirtualKeyCode::H {
+            self.help_visible = !self.help_visible;
+            return;
+        }
+        if self.help_visible {
+            return;
+        }
 
         match self.mode {
             Mode::Menu => {
                 if pressed {
                     match key {
-                        VirtualKeyCode::Key1 => self.start_level(0),
-                        VirtualKeyCode::Key2 => self.start_level(1),
-                        VirtualKeyCode::Key3 => self.start_level(2),
+                        VirtualKeyCode::Key0 => self.select_level(TUTORIAL_LEVEL),
+                        VirtualKeyCode::Key1 => self.select_level(0),
+                        VirtualKeyCode::Key2 => self.select_level(1),
+                        VirtualKeyCode::Key3 => self.select_level(2),
+                        VirtualKeyCode::O => {
+                            self.settings_cursor = 0;
+                            self.mode = Mode::Settings;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Mode::Settings => {
+                if pressed {
+                    match key {
+                        VirtualKeyCode::Up => {
+                            self.settings_cursor =
+                                (self.settings_cursor + Settings::ITEM_COUNT - 1) % Settings::ITEM_COUNT;
+                        }
+                        VirtualKeyCode::Down => {
+                            self.settings_cursor = (self.settings_cursor + 1) % Settings::ITEM_COUNT;
+                        }
+                        VirtualKeyCode::Left => self.change_setting(-1),
+                        VirtualKeyCode::Right => self.change_setting(1),
+                        VirtualKeyCode::Return => {
+                            // Volver al menú
+                            self.mode = Mode::Menu;
+                        }
                         _ => {}
                     }
                 }
             }
-            Mode::Win => {
+            Mode::WinSequence => {
+                // Cualquier tecla salta el festejo
+                if pressed {
+                    self.mode = Mode::Win;
+                }
+            }
+            Mode::Win | Mode::Error(_) => {
                 if pressed && key == VirtualKeyCode::Return {
                     self.mode = Mode::Menu;
                 }
             }
+            Mode::LevelPreview => {
+                if pressed && key == VirtualKeyCode::Return {
+                    self.mode = Mode::Playing;
+                }
+            }
             Mode::GameOver => {
                 if pressed {
                     match key {
@@ -173,6 +928,7 @@ impl Game {
                         }
                         VirtualKeyCode::Return => {
                             // Volver al menú
+                            self.spectating = false;
                             self.mode = Mode::Menu;
                         }
                         _ => {}
@@ -195,62 +951,461 @@ impl Game {
                 }
             }
             Mode::Playing => {
+                // En modo grilla los giros se encolan en vez de girar al instante
+                if pressed && self.settings.grid_movement && self.queue_grid_turn(key) {
+                    return;
+                }
                 if pressed && key == VirtualKeyCode::P {
                     // Pausa
                     self.mode = Mode::Paused;
+                    if self.tutorial_step == Some(TUTORIAL_PAUSE_STEP) {
+                        self.tutorial_step = Some(TUTORIAL_PAUSE_STEP + 1);
+                    }
+                } else if pressed && key == VirtualKeyCode::Tab && self.tutorial_step.is_some() {
+                    // Saltar el tutorial e ir al primer nivel
+                    self.start_level(0);
+                } else if pressed && key == VirtualKeyCode::L {
+                    self.show_legend = !self.show_legend;
+                } else if pressed && key == VirtualKeyCode::V {
+                    self.view_mode = match self.view_mode {
+                        ViewMode::FirstPerson => ViewMode::TopDown,
+                        ViewMode::TopDown => ViewMode::FirstPerson,
+                    };
+                } else if pressed && key == VirtualKeyCode::Space {
+                    self.ping();
+                } else if pressed && key == VirtualKeyCode::Home {
+                    self.reset_view();
+                } else if pressed && self.debug {
+                    self.on_debug_key(key);
                 }
             }
         }
     }
 
+    // Cámara al estado de salida: sin inclinación, FOV por defecto y mirando hacia
+    // donde arranca el nivel. En modo grilla el rumbo lo manda la grilla.
+    pub fn reset_view(&mut self) {
+        self.pitch = 0.0;
+        if self.settings.grid_movement {
+            self.player.face(self.grid.view_angle);
+        } else {
+            let facing = self.level.spawn_dir.map_or(std::f32::consts::PI, f32::to_radians);
+            self.player.face(facing);
+        }
+        self.view_reset_flash = 1.5;
+    }
+
+    // Radar: marca por un momento a los fantasmas cercanos, con usos limitados
+    fn ping(&mut self) {
+        if self.ping_charges == 0 || self.ping_cooldown > 0.0 {
+            return;
+        }
+        self.ping_charges -= 1;
+        self.ping_time = PING_DURATION;
+        self.ping_cooldown = PING_COOLDOWN;
+        self.audio.play_sfx("assets/sfx/ping.wav");
+    }
+
+    // ¿El radar en curso alcanza a este fantasma? (los ojos no cuentan)
+    fn is_pinged(&self, g: &Ghost) -> bool {
+        let (dx, dy) = (g.x - self.player.x, g.y - self.player.y);
+        self.ping_time > 0.0 && g.state != GhostState::Eyes && dx * dx + dy * dy <= PING_RADIUS * PING_RADIUS
+    }
+
+    // Onda del radar en el minimapa: un anillo que crece desde el jugador hasta
+    // el alcance, recortado a la caja del minimapa. `s` = píxeles por celda.
+    fn draw_ping_ring(
+        &self,
+        frame: &mut [u8],
+        w: i32,
+        h: i32,
+        center: (f32, f32),
+        s: f32,
+        clip: (i32, i32, i32, i32),
+    ) {
+        if self.ping_time <= 0.0 {
+            return;
+        }
+        let r = PING_RADIUS * (1.0 - self.ping_time / PING_DURATION) * s;
+        let (x0, y0, x1, y1) = clip;
+        let steps = ((r * std::f32::consts::TAU) as i32).max(8);
+        for i in 0..steps {
+            let a = i as f32 / steps as f32 * std::f32::consts::TAU;
+            let x = (center.0 + a.cos() * r) as i32;
+            let y = (center.1 + a.sin() * r) as i32;
+            if x >= x0 && y >= y0 && x < x1 && y < y1 {
+                rect_fill(frame, w, h, x, y, 1, 1, [120, 255, 160, 255]);
+            }
+        }
+    }
+
+    // Debug (F5): los rayos de este cuadro sobre el minimapa, uno cada
+    // DEBUG_RAY_EVERY columnas, del jugador al punto de choque. El punto sale de
+    // la distancia perpendicular del depth buffer: choque = jugador + dir_rayo * dist.
+    fn draw_debug_rays(
+        &self,
+        frame: &mut [u8],
+        w: i32,
+        h: i32,
+        to_screen: &dyn Fn(f32, f32) -> (f32, f32),
+        clip: (i32, i32, i32, i32),
+    ) {
+        let p = &self.player;
+        let cols = self.depth.cols.len();
+        let (x0, y0, x1, y1) = clip;
+        let plot = |frame: &mut [u8], x: f32, y: f32, color: [u8; 4]| {
+            let (x, y) = (x as i32, y as i32);
+            if x >= x0 && y >= y0 && x < x1 && y < y1 {
+                rect_fill(frame, w, h, x, y, 1, 1, color);
+            }
+        };
+        let (sx, sy) = to_screen(p.x, p.y);
+        for col in (0..cols).step_by(DEBUG_RAY_EVERY) {
+            let camera_x = 2.0 * col as f32 / cols as f32 - 1.0;
+            let (rdx, rdy) = (p.dir_x + p.plane_x * camera_x, p.dir_y + p.plane_y * camera_x);
+            let dist = self.depth.cols[col].min(self.render_opts.max_ray_distance);
+            let (ex, ey) = to_screen(p.x + rdx * dist, p.y + rdy * dist);
+            let n = (ex - sx).abs().max((ey - sy).abs()).ceil().max(1.0) as i32;
+            for i in 0..n {
+                let t = i as f32 / n as f32;
+                plot(frame, sx + (ex - sx) * t, sy + (ey - sy) * t, [80, 255, 80, 255]);
+            }
+            plot(frame, ex, ey, [255, 80, 80, 255]);
+        }
+    }
+
+    // Escape cierra la ayuda si está abierta; devuelve false si no la usó
+    // (entonces main sale del juego)
+    pub fn on_escape(&mut self) -> bool {
+        if self.help_visible {
+            self.help_visible = false;
+            return true;
+        }
+        false
+    }
+
     pub fn on_mouse_delta(&mut self, dx: f32) {
-        if self.mode != Mode::Playing {
+        if self.help_visible || (self.mode != Mode::Playing && !self.spectating) {
+            return;
+        }
+        // En la grilla la cámara solo mira a los puntos cardinales
+        if self.mode == Mode::Playing && self.settings.grid_movement {
+            return;
+        }
+        let mut angle = -dx * self.settings.mouse_sensitivity;
+        if self.settings.mouse_accel {
+            angle *= (1.0 + dx.abs() * MOUSE_ACCEL_GAIN).min(MOUSE_ACCEL_MAX);
+        }
+        // Sin suavizado se gira en el acto, como siempre; con suavizado el giro
+        // se acumula y update() lo va soltando de a poco
+        if self.settings.mouse_smoothing > 0.0 {
+            self.mouse_pending += angle;
+        } else {
+            self.rotate(angle);
+        }
+    }
+
+    // Movimiento vertical del mouse: sube o baja la mirada (invertido si se pidió).
+    // La cámara todavía no se inclina; pitch queda listo para cuando lo haga.
+    pub fn on_mouse_pitch(&mut self, dy: f32) {
+        if dy == 0.0 || self.help_visible || (self.mode != Mode::Playing && !self.spectating) {
+            return;
+        }
+        let sign = if self.settings.invert_mouse_y { 1.0 } else { -1.0 };
+        self.pitch = (self.pitch + sign * dy * self.settings.mouse_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // Aplica parte del giro pendiente del mouse; el resto decae en los frames siguientes
+    fn apply_mouse_smoothing(&mut self, dt: f32) {
+        if self.mouse_pending == 0.0 {
             return;
         }
-        let angle = -dx * self.mouse_sensitivity;
+        if self.help_visible || (self.mode != Mode::Playing && !self.spectating) {
+            self.mouse_pending = 0.0;
+            return;
+        }
+        // Fracción independiente del framerate: con suavizado s queda s^(dt*60) pendiente
+        let keep = self.settings.mouse_smoothing.clamp(0.0, 0.99).powf(dt * 60.0);
+        let angle = self.mouse_pending * (1.0 - keep);
+        self.mouse_pending -= angle;
+        if self.mouse_pending.abs() < 1e-5 {
+            self.mouse_pending = 0.0;
+        }
         self.rotate(angle);
     }
 
+    // Clic izquierdo: "usa" lo que está frente a la mira si está a alcance
+    pub fn on_click(&mut self) {
+        if self.mode != Mode::Playing {
+            return;
+        }
+        let p = &self.player;
+        let hit = cast_ray(&self.level, p.x, p.y, p.dir_x, p.dir_y);
+        if hit.tile == MISS_TILE || hit.dist > USE_RANGE || !is_interactable(hit.tile) {
+            return;
+        }
+        // Aquí se activará la puerta/interruptor en (hit.map_x, hit.map_y)
+        self.audio.play_sfx("assets/sfx/use.wav");
+    }
+
+    // Pantalla de error en vez de cortar el programa (p. ej. un --level que no
+    // se pudo leer). Enter vuelve al menú con los niveles incluidos.
+    pub fn show_error(&mut self, message: String) {
+        self.mode = Mode::Error(message);
+    }
+
+    // Nivel elegido en el menú: se prepara y, si está activada, se muestra la
+    // vista previa del mapa en vez de empezar directamente
+    fn select_level(&mut self, index: usize) {
+        self.start_level(index);
+        if self.settings.level_preview {
+            self.mode = Mode::LevelPreview;
+        }
+    }
+
+    // Juega un nivel cargado desde afuera; no cuenta para la campaña ni los récords
+    pub fn start_custom_level(&mut self, level: Level) {
+        self.custom_level = Some(level);
+        self.start_level(CUSTOM_LEVEL);
+    }
+
     fn start_level(&mut self, index: usize) {
         self.level_index = index;
-        self.level = get_level(index);
+        self.level = match &self.custom_level {
+            Some(custom) if index == CUSTOM_LEVEL => custom.clone(),
+            _ => get_level(index),
+        };
+        self.tutorial_step = (index == TUTORIAL_LEVEL).then_some(0);
+        self.rooms = self.level.segment_rooms();
+        self.update_echo();
         let (px, py) = self.level.spawn;
-        self.player.x = px as f32 + 0.5;
-        self.player.y = py as f32 + 0.5;
-        self.player.dir_x = -1.0;
-        self.player.dir_y = 0.0;
-        self.player.plane_x = 0.0;
-        self.player.plane_y = 0.66;
-        self.sprites = Self::build_sprites_for_level(&self.level);
+        (self.player.x, self.player.y) = self.level.cell_center(px, py);
+        self.player.vel_x = 0.0;
+        self.player.vel_y = 0.0;
+        self.grid = GridMove::default();
+        let facing = self.level.spawn_dir.map_or(std::f32::consts::PI, f32::to_radians);
+        self.player.face(facing);
+        let no_ghosts = self.settings.practice;
+        Self::build_sprites_for_level(&self.level, &mut self.pellets, &mut self.ghosts, no_ghosts);
+        self.particles.clear();
+        self.sync_prev_positions();
+        self.accumulator = 0.0;
 
         // Recalcular contadores de monedas
-        self.total_pellets = self.sprites.iter().filter(|s| s.kind == SpriteKind::Pellet).count();
+        self.total_pellets = self.pellets.len();
         self.pellets_remaining = self.total_pellets;
 
         self.mode = Mode::Playing;
-        self.lives = 3;             // 3 vidas por nivel
+        self.lives = self.settings.starting_lives;
         self.invincible_time = 0.0; // sin invulnerabilidad al inicio
+        self.stun_time = 0.0;
+        self.bump_count = 0;
         self.death_anim_t = 0.0;
+        self.spectating = false;
         self.time = 0.0;
+        self.time_left = if self.settings.timed_mode { self.level.time_limit } else { None };
+        self.time_up = false;
+        self.score = 0;
+        self.ghosts_eaten = 0;
+        self.ghost_sightings.clear();
+        self.trail = vec![f32::NEG_INFINITY; (self.level.w * self.level.h) as usize];
+        // La habitación del inicio ya cuenta como visitada: no suena al aparecer
+        self.rooms_entered = vec![false; self.rooms.count];
+        if let Some(id) = self.room_at(self.player.x, self.player.y) {
+            self.rooms_entered[id] = true;
+        }
+        self.combo_count = 0;
+        self.combo_timer = 0.0;
+        self.next_extra_life = EXTRA_LIFE_EVERY;
+        self.extra_life_flash = 0.0;
+        self.aggression_level = 0;
+        self.aggression_flash = 0.0;
+        self.view_reset_flash = 0.0;
+        self.freeze_time = 0.0;
+        self.ping_time = 0.0;
+        self.ping_cooldown = 0.0;
+        self.ping_charges = PING_CHARGES;
+        self.rush_time = 0.0;
+        self.rush_done = false;
+        self.hazard_damage = 0.0;
+        self.hazard_cooldown = 0.0;
+        self.lean = 0.0;
+        self.shake_time = 0.0;
+
+        self.audio.play_music_layers("assets/music/theme.ogg", "assets/music/theme_intense.ogg");
+    }
 
-        self.audio.play_music_loop("assets/music/theme.ogg");
+    // Un cuadro completo sin depender de la ventana: aplica la entrada acumulada
+    // y avanza la simulación `dt` segundos. Devuelve false si se pidió salir
+    // (Escape sin ningún overlay que cerrar).
+    pub fn tick(&mut self, dt: f32, input: &InputState) -> bool {
+        for &(key, pressed) in &input.keys {
+            if pressed && key == VirtualKeyCode::Escape {
+                if !self.on_escape() {
+                    return false;
+                }
+                continue;
+            }
+            self.on_key(key, pressed);
+        }
+        for &(dx, dy) in &input.mouse_deltas {
+            self.on_mouse_delta(dx);
+            self.on_mouse_pitch(dy);
+        }
+        for _ in 0..input.clicks {
+            self.on_click();
+        }
+        self.update(dt);
+        true
     }
 
+    // Avanza la simulación en pasos fijos; el sobrante se usa para interpolar al dibujar
     pub fn update(&mut self, dt: f32) {
-        self.fps_count += 1;
-        self.fps_acc += dt;
-        if self.fps_acc >= 1.0 {
-            self.fps = self.fps_count as f32 / self.fps_acc;
-            self.fps_acc = 0.0;
-            self.fps_count = 0;
+        if dt > 0.0 {
+            self.fps_raw = 1.0 / dt;
+            // El peso depende de dt para que el suavizado dure lo mismo a cualquier framerate
+            let a = 1.0 - (-dt / FPS_SMOOTHING).exp();
+            self.fps = if self.fps > 0.0 { self.fps + (self.fps_raw - self.fps) * a } else { self.fps_raw };
+        }
+
+        self.audio.tension = self.music_tension();
+        self.audio.update(dt);
+        if let Some(t) = self.settings_save_in.as_mut() {
+            *t -= dt;
+            if *t <= 0.0 {
+                self.save_settings();
+            }
+        }
+        self.shake_time = (self.shake_time - dt).max(0.0);
+        self.pellet_sfx_cooldown = (self.pellet_sfx_cooldown - dt).max(0.0);
+        self.bump_sfx_cooldown = (self.bump_sfx_cooldown - dt).max(0.0);
+        self.apply_mouse_smoothing(dt);
+
+        // Paso a paso (debug): solo corre un paso fijo cuando se pide con F7, y se
+        // dibuja sin interpolar para ver exactamente el estado de ese paso
+        if self.debug_paused && self.mode == Mode::Playing {
+            if self.step_requested {
+                self.sync_prev_positions();
+                self.step(FIXED_DT);
+                self.anim_clock += FIXED_DT;
+            }
+            self.step_requested = false;
+            self.sync_prev_positions();
+            self.accumulator = 0.0;
+        } else {
+            self.accumulator += dt.min(MAX_FRAME_DT);
+            while self.accumulator >= FIXED_DT {
+                self.sync_prev_positions();
+                self.step(FIXED_DT);
+                self.accumulator -= FIXED_DT;
+            }
+            self.anim_clock += dt;
+        }
+        self.render_opts.anim_time = self.anim_clock;
+        self.render_opts.freeze_time = self.freeze_time;
+        self.render_opts.ping_time = self.ping_time;
+        self.render_opts.ping_radius = if self.ping_time > 0.0 { PING_RADIUS } else { 0.0 };
+    }
+
+    // Tensión para la música adaptativa: sube a medida que el fantasma peligroso
+    // más cercano se acerca; la estampida la lleva al máximo
+    fn music_tension(&self) -> f32 {
+        if self.mode != Mode::Playing || self.freeze_time > 0.0 {
+            return 0.0;
+        }
+        if self.rush_time > 0.0 {
+            return 1.0;
+        }
+        let nearest = self
+            .ghosts
+            .iter()
+            .filter(|g| g.state == GhostState::Normal)
+            .map(|g| ((g.x - self.player.x).powi(2) + (g.y - self.player.y).powi(2)).sqrt())
+            .fold(f32::INFINITY, f32::min);
+        1.0 - ((nearest - TENSION_NEAR) / (TENSION_FAR - TENSION_NEAR)).clamp(0.0, 1.0)
+    }
+
+    // Guarda las posiciones actuales como "anteriores" (también tras teletransportes)
+    fn sync_prev_positions(&mut self) {
+        self.player.prev_x = self.player.x;
+        self.player.prev_y = self.player.y;
+        for g in self.ghosts.iter_mut() {
+            g.prev_x = g.x;
+            g.prev_y = g.y;
+        }
+    }
+
+    // Cámara y fantasmas interpolados entre el paso anterior y el actual
+    fn interpolated_view(&self) -> (Player, Vec<Ghost>) {
+        let a = (self.accumulator / FIXED_DT).clamp(0.0, 1.0);
+        let lerp = |from: f32, to: f32| from + (to - from) * a;
+
+        let mut cam = self.player.clone();
+        cam.x = lerp(self.player.prev_x, self.player.x);
+        cam.y = lerp(self.player.prev_y, self.player.y);
+        let (ox, oy) = self.lean_offset(cam.x, cam.y);
+        cam.x += ox;
+        cam.y += oy;
+        if self.mode == Mode::WinSequence {
+            // La cámara retrocede de a poco para orbitar, sin meterse en la pared de atrás
+            let back = cast_ray(&self.level, cam.x, cam.y, -cam.dir_x, -cam.dir_y).dist - PLAYER_RADIUS;
+            let r = (WIN_ORBIT_RADIUS * (self.win_seq_t / WIN_SEQUENCE_TIME).min(1.0)).min(back.max(0.0));
+            cam.x -= cam.dir_x * r;
+            cam.y -= cam.dir_y * r;
         }
 
+        let ghosts = self
+            .ghosts
+            .iter()
+            .map(|g| {
+                let mut v = g.clone();
+                v.x = lerp(g.prev_x, g.x);
+                v.y = lerp(g.prev_y, g.y);
+                v
+            })
+            .collect();
+        (cam, ghosts)
+    }
+
+    fn step(&mut self, dt: f32) {
+        if self.help_visible {
+            return;
+        }
         match self.mode {
-            Mode::Menu => {}
-            Mode::Win => {}
+            Mode::Menu => {
+                if self.attract {
+                    self.autopilot(dt);
+                    self.update_sprites(dt);
+                    update_particles(&mut self.particles, dt);
+                    self.time += dt;
+                } else {
+                    self.menu_idle += dt;
+                    if self.menu_idle >= ATTRACT_IDLE_TIME {
+                        self.start_attract();
+                    }
+                }
+            }
+            Mode::Settings => {}
+            Mode::WinSequence => {
+                self.win_seq_t += dt;
+                self.rotate(WIN_ORBIT_SPEED * dt);
+                update_particles(&mut self.particles, dt);
+                if self.win_seq_t >= WIN_SEQUENCE_TIME {
+                    self.mode = Mode::Win;
+                }
+            }
+            Mode::Win | Mode::LevelPreview | Mode::Error(_) => {}
             Mode::GameOver => {
                 // Animación de Game Over
                 self.death_anim_t += dt;
+
+                // Terminado el fade, cámara libre sin colisiones ni daño
+                if !self.spectating && self.settings.spectator && self.death_anim_t >= 2.0 {
+                    self.spectating = true;
+                }
+                if self.spectating {
+                    self.handle_input(dt, false);
+                }
             }
             Mode::Paused => {
                 // En pausa no actualizamos lógica ni temporizadores de juego.
@@ -260,25 +1415,145 @@ impl Game {
                 if self.invincible_time > 0.0 {
                     self.invincible_time = (self.invincible_time - dt).max(0.0);
                 }
+                self.bump_memory = (self.bump_memory - dt).max(0.0);
+                if self.bump_memory <= 0.0 {
+                    self.bump_count = 0;
+                }
+
+                self.extra_life_flash = (self.extra_life_flash - dt).max(0.0);
+                self.view_reset_flash = (self.view_reset_flash - dt).max(0.0);
+
+                // Cuanto más dura el nivel, más rápidos los fantasmas
+                let level = ((self.time / AGGRESSION_INTERVAL) as u32).min(AGGRESSION_MAX_LEVEL);
+                if level > self.aggression_level {
+                    self.aggression_level = level;
+                    self.aggression_flash = 2.0;
+                }
+                self.aggression_flash = (self.aggression_flash - dt).max(0.0);
+                self.freeze_time = (self.freeze_time - dt).max(0.0);
+                self.ping_time = (self.ping_time - dt).max(0.0);
+                self.ping_cooldown = (self.ping_cooldown - dt).max(0.0);
+                self.rush_time = (self.rush_time - dt).max(0.0);
+
+                // El combo se pierde si pasa la ventana sin recoger monedas
+                if self.combo_timer > 0.0 {
+                    self.combo_timer -= dt;
+                    if self.combo_timer <= 0.0 {
+                        self.combo_timer = 0.0;
+                        self.combo_count = 0;
+                    }
+                }
 
-                self.handle_input(dt);
+                if self.stun_time > 0.0 {
+                    self.update_stun(dt);
+                } else {
+                    self.handle_input(dt, true);
+                }
+                self.update_lean(dt);
                 self.update_sprites(dt);
+                self.update_ghost_sightings();
+                self.update_trail();
+                self.update_room_entry();
+                update_particles(&mut self.particles, dt);
                 self.check_collisions_and_pickups();
+                self.check_rush();
+                self.update_hazard(dt);
+                self.update_tutorial();
 
-                // Victoria al recolectar todas las monedas
-                if self.pellets_remaining == 0 {
-                    self.mode = Mode::Win;
-                    self.audio.play_sfx("assets/sfx/win.wav");
+                // Contrarreloj
+                if let Some(t) = self.time_left.as_mut() {
+                    *t = (*t - dt).max(0.0);
+                }
+
+                // La victoria tiene prioridad si coincide con la derrota
+                if self.check_win() {
+                    self.mode = Mode::WinSequence;
+                    self.win_seq_t = 0.0;
+                    self.record_level_stats();
+                    self.audio.play_sfx_ducked("assets/sfx/win.wav");
+                } else if self.check_loss() {
+                    self.time_up = self.lives > 0;
+                    self.mode = Mode::GameOver;
+                    self.death_anim_t = 0.0;
+                    self.audio.play_sfx_ducked("assets/sfx/game_over.wav");
                 }
             }
         }
     }
 
-    fn handle_input(&mut self, dt: f32) {
+    // Prepara la demo del menú en el siguiente nivel de la campaña, sin tocar
+    // el modo ni el estado de la partida (start_level lo rehace todo al jugar)
+    fn start_attract(&mut self) {
+        self.attract = true;
+        self.attract_level = (self.attract_level + 1) % LEVEL_COUNT;
+        self.level = get_level(self.attract_level);
+        self.rooms = self.level.segment_rooms();
+        self.update_echo();
+        Self::build_sprites_for_level(&self.level, &mut self.pellets, &mut self.ghosts, false);
+        self.particles.clear();
+        let (px, py) = self.level.spawn;
+        (self.player.x, self.player.y) = self.level.cell_center(px, py);
+        let facing = self.level.spawn_dir.map_or(std::f32::consts::PI, f32::to_radians);
+        self.player.face(facing);
+        self.sync_prev_positions();
+    }
+
+    // Piloto automático de la demo: avanza con un leve vaivén y, si tiene una
+    // pared adelante, gira hacia el lado libre hasta poder seguir
+    fn autopilot(&mut self, dt: f32) {
+        let p = &self.player;
+        let free_towards = |angle: f32| {
+            let (c, s) = (angle.cos(), angle.sin());
+            let (dx, dy) = (p.dir_x * c - p.dir_y * s, p.dir_x * s + p.dir_y * c);
+            can_move_to(&self.level, p.x + dx * 0.8, p.y + dy * 0.8, PLAYER_RADIUS)
+        };
+        let (ahead, left) = (free_towards(0.0), free_towards(0.6));
+        let rot = p.rot_speed * dt;
+        let speed = p.move_speed * ATTRACT_SPEED * dt;
+        let (dx, dy) = (p.dir_x * speed, p.dir_y * speed);
+        if ahead {
+            self.attract_turn = if left { 1.0 } else { -1.0 };
+            self.try_move(dx, dy);
+            self.rotate((self.time * 0.7).sin() * 0.3 * rot);
+        } else {
+            self.rotate(self.attract_turn * rot);
+        }
+    }
+
+    // Avanza el tutorial cuando el jugador hizo lo que pide el paso actual
+    // (la pausa se detecta en on_key)
+    fn update_tutorial(&mut self) {
+        let Some(step) = self.tutorial_step else {
+            return;
+        };
+        let (sx, sy) = self.level.cell_center(self.level.spawn.0, self.level.spawn.1);
+        let done = match step {
+            0 => (self.player.x - sx).hypot(self.player.y - sy) > self.level.tile_scale,
+            1 => {
+                let facing = self.level.spawn_dir.map_or(std::f32::consts::PI, f32::to_radians);
+                self.player.dir_x * facing.cos() + self.player.dir_y * facing.sin() < 0.85
+            }
+            2 => self.score > 0,
+            _ => false,
+        };
+        if done {
+            self.tutorial_step = Some(step + 1);
+        }
+    }
+
+    // collide = false mueve la cámara libremente (modo espectador)
+    fn handle_input(&mut self, dt: f32, collide: bool) {
+        if collide && self.settings.grid_movement {
+            self.grid_step(dt);
+            return;
+        }
+        let tank = self.control_scheme == ControlScheme::Tank;
         let w_down = self.is_down(VirtualKeyCode::W);
         let s_down = self.is_down(VirtualKeyCode::S);
-        let q_down = self.is_down(VirtualKeyCode::Q) || self.is_down(VirtualKeyCode::Left);
-        let e_down = self.is_down(VirtualKeyCode::E) || self.is_down(VirtualKeyCode::Right);
+        let q_down = (tank && self.is_down(VirtualKeyCode::Q)) || self.is_down(VirtualKeyCode::Left);
+        let e_down = (tank && self.is_down(VirtualKeyCode::E)) || self.is_down(VirtualKeyCode::Right);
+        let a_down = !tank && self.is_down(VirtualKeyCode::A);
+        let d_down = !tank && self.is_down(VirtualKeyCode::D);
 
         let (dir_x, dir_y, move_speed, rot_speed) =
             (self.player.dir_x, self.player.dir_y, self.player.move_speed, self.player.rot_speed);
@@ -294,6 +1569,15 @@ impl Game {
             move_x -= dir_x * move_speed * dt;
             move_y -= dir_y * move_speed * dt;
         }
+        if a_down || d_down {
+            // El plano de cámara apunta a la derecha de la pantalla
+            let plane_len = (self.player.plane_x.powi(2) + self.player.plane_y.powi(2)).sqrt();
+            let right_x = self.player.plane_x / plane_len;
+            let right_y = self.player.plane_y / plane_len;
+            let side = if d_down { 1.0 } else { 0.0 } - if a_down { 1.0 } else { 0.0 };
+            move_x += right_x * side * move_speed * dt;
+            move_y += right_y * side * move_speed * dt;
+        }
 
         let mut rot = 0.0;
         if q_down {
@@ -306,84 +1590,233 @@ impl Game {
             self.rotate(rot);
         }
 
-        self.try_move(move_x, move_y);
+        if collide {
+            // Sobre hielo se conserva la inercia: la velocidad se acerca de a poco
+            // a la pedida, así que frenar y cambiar de rumbo cuesta
+            let (want_x, want_y) = (move_x / dt, move_y / dt);
+            let p = &mut self.player;
+            let (cx, cy) = self.level.cell_at(p.x, p.y);
+            if self.level.is_ice(cx, cy) {
+                let k = (ICE_GRIP * dt).min(1.0);
+                p.vel_x += (want_x - p.vel_x) * k;
+                p.vel_y += (want_y - p.vel_y) * k;
+            } else {
+                p.vel_x = want_x;
+                p.vel_y = want_y;
+            }
+            let (old_x, old_y) = (p.x, p.y);
+            let blocked = self.try_move(self.player.vel_x * dt, self.player.vel_y * dt);
+            if blocked && self.bump_sfx_cooldown <= 0.0 {
+                self.audio.play_sfx("assets/sfx/bump.wav");
+                self.bump_sfx_cooldown = BUMP_SFX_INTERVAL;
+            }
+            // Chocar con una pared corta la inercia en ese eje
+            self.player.vel_x = (self.player.x - old_x) / dt;
+            self.player.vel_y = (self.player.y - old_y) / dt;
+        } else {
+            // Sin colisiones, pero sin salir del mapa
+            self.player.x = (self.player.x + move_x).clamp(0.01, self.level.w as f32 - 0.01);
+            self.player.y = (self.player.y + move_y).clamp(0.01, self.level.h as f32 - 0.01);
+        }
     }
 
-    fn rotate(&mut self, angle: f32) {
-        let p = &mut self.player;
-        let old_dir_x = p.dir_x;
-        p.dir_x = p.dir_x * angle.cos() - p.dir_y * angle.sin();
-        p.dir_y = old_dir_x * angle.sin() + p.dir_y * angle.cos();
+    // Teclas de giro en modo grilla: quedan en espera hasta la próxima
+    // intersección. Devuelve false si la tecla no es de movimiento.
+    fn queue_grid_turn(&mut self, key: VirtualKeyCode) -> bool {
+        let turn = match key {
+            VirtualKeyCode::Q | VirtualKeyCode::A | VirtualKeyCode::Left => 1,
+            VirtualKeyCode::E | VirtualKeyCode::D | VirtualKeyCode::Right => 3,
+            VirtualKeyCode::S | VirtualKeyCode::Down => 2,
+            _ => return false,
+        };
+        self.grid.queued_turn = Some(turn);
+        true
+    }
 
-        let old_plane_x = p.plane_x;
-        p.plane_x = p.plane_x * angle.cos() - p.plane_y * angle.sin();
-        p.plane_y = old_plane_x * angle.sin() + p.plane_y * angle.cos();
+    fn grid_cell_free(&self, x: i32, y: i32) -> bool {
+        self.level.tile(x, y) == 0
     }
 
-    fn try_move(&mut self, dx: f32, dy: f32) {
-        let new_x = self.player.x + dx;
-        let new_y = self.player.y + dy;
+    // Un paso del movimiento en grilla: W avanza de celda en celda; los giros
+    // esperan a un centro con salida en esa dirección, y la media vuelta es inmediata
+    fn grid_step(&mut self, dt: f32) {
+        let quarter = std::f32::consts::FRAC_PI_2;
+        if !self.grid.active {
+            // Alinearse: ir al centro de la celda actual mirando al cardinal más cercano
+            let angle = self.player.dir_y.atan2(self.player.dir_x);
+            self.grid = GridMove {
+                active: true,
+                facing: ((angle / quarter).round() as i32).rem_euclid(4) as usize,
+                target: Some(self.level.cell_at(self.player.x, self.player.y)),
+                queued_turn: None,
+                view_angle: angle,
+            };
+        }
 
-        if !self.is_wall(new_x, self.player.y) {
-            self.player.x = new_x;
+        // Media vuelta en medio de un tramo: volver a la celda de la que se salió.
+        // Solo si todavía no se llegó a la celda destino (al alinearse el destino
+        // es la celda actual y detrás puede haber pared); si no, espera al centro.
+        if self.grid.queued_turn == Some(2) {
+            if let Some(target) = self.grid.target {
+                let cell = self.level.cell_at(self.player.x, self.player.y);
+                let (dx, dy) = CARDINALS[self.grid.facing];
+                let back = (target.0 - dx, target.1 - dy);
+                if target != cell && back == cell {
+                    self.grid.target = Some(back);
+                    self.grid.facing = (self.grid.facing + 2) % 4;
+                    self.grid.queued_turn = None;
+                }
+            }
         }
-        if !self.is_wall(self.player.x, new_y) {
-            self.player.y = new_y;
+
+        let forward = self.is_down(VirtualKeyCode::W) || self.is_down(VirtualKeyCode::Up);
+        let mut travel = self.player.move_speed * dt;
+        loop {
+            if let Some((tx, ty)) = self.grid.target {
+                let (cx, cy) = self.level.cell_center(tx, ty);
+                let (dx, dy) = (cx - self.player.x, cy - self.player.y);
+                let d = (dx * dx + dy * dy).sqrt();
+                if d > travel {
+                    self.player.x += dx / d * travel;
+                    self.player.y += dy / d * travel;
+                    break;
+                }
+                self.player.x = cx;
+                self.player.y = cy;
+                travel -= d;
+                self.grid.target = None;
+            }
+
+            // Quieto en un centro: aplicar el giro si hay salida (o si igual no se avanza)
+            let cell = self.level.cell_at(self.player.x, self.player.y);
+            let ahead = |facing: usize| (cell.0 + CARDINALS[facing].0, cell.1 + CARDINALS[facing].1);
+            let can_go = |g: &Self, facing: usize| {
+                let (nx, ny) = ahead(facing);
+                g.grid_cell_free(nx, ny)
+            };
+            if let Some(turn) = self.grid.queued_turn {
+                let turned = (self.grid.facing + turn) % 4;
+                if can_go(self, turned) || !forward || !can_go(self, self.grid.facing) {
+                    self.grid.facing = turned;
+                    self.grid.queued_turn = None;
+                }
+            }
+            if forward && travel > 0.0 && can_go(self, self.grid.facing) {
+                self.grid.target = Some(ahead(self.grid.facing));
+            } else {
+                break;
+            }
         }
+
+        // La cámara gira suave hacia el cardinal elegido
+        let goal = self.grid.facing as f32 * quarter;
+        let mut diff = goal - self.grid.view_angle;
+        diff = (diff + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        let max_turn = GRID_TURN_SPEED * dt;
+        self.grid.view_angle += diff.clamp(-max_turn, max_turn);
+        self.player.face(self.grid.view_angle);
     }
 
-    fn is_wall(&self, x: f32, y: f32) -> bool {
-        if x < 0.0 || y < 0.0 {
-            return true;
-        }
-        let xi = x as i32;
-        let yi = y as i32;
-        if xi < 0 || yi < 0 || xi >= self.level.w || yi >= self.level.h {
-            return true;
-        }
-        self.level.tile(xi, yi) > 0
+    fn rotate(&mut self, angle: f32) {
+        self.player.rotate(angle);
+    }
+
+    // Devuelve true si una pared frenó parte del movimiento
+    fn try_move(&mut self, dx: f32, dy: f32) -> bool {
+        let (x, y) = slide_move(&self.level, self.player.x, self.player.y, dx, dy, PLAYER_RADIUS);
+        let blocked = (x - (self.player.x + dx)).abs() > 1e-4 || (y - (self.player.y + dy)).abs() > 1e-4;
+        self.player.x = x;
+        self.player.y = y;
+        blocked
     }
 
     fn update_sprites(&mut self, dt: f32) {
-        // 1) Animación de pellets
-        for s in self.sprites.iter_mut() {
-            if s.kind == SpriteKind::Pellet {
-                s.anim_time += dt;
-                if s.anim_time > 0.5 {
-                    s.anim_time = 0.0;
-                    s.anim_frame = (s.anim_frame + 1) % 2;
-                }
-            }
+        // Las monedas se animan al dibujar con el reloj global (render_opts.anim_time)
+
+        // Congelados: ni se mueven ni se animan hasta que pase el efecto
+        if self.freeze_time > 0.0 {
+            return;
         }
 
-        // 2) IA de fantasmas con dispersión y separación
-        let ghost_positions: Vec<(usize, f32, f32)> = self
-            .sprites
-            .iter()
-            .enumerate()
-            .filter_map(|(i, s)| if s.kind == SpriteKind::Ghost { Some((i, s.x, s.y)) } else { None })
-            .collect();
+        // IA de fantasmas con dispersión y separación
+        let ghost_positions: Vec<(f32, f32)> = self.ghosts.iter().map(|g| (g.x, g.y)).collect();
 
-        let scatter_r = 1.6_f32; // offset alrededor del jugador
-        let sep_r = 0.9_f32; // separación entre fantasmas
-        let speed = 1.35_f32;
+        let GhostTuning { speed, scatter_r, sep_r } = self.ghost_tuning;
+        let speed = speed * (1.0 + AGGRESSION_STEP * self.aggression_level as f32);
+        let rush = self.rush_time > 0.0;
+        let speed = if rush { speed * self.settings.difficulty.rush_speed() } else { speed };
 
         let mut rng = rand::thread_rng();
 
-        for (k, (gi, gx, gy)) in ghost_positions.iter().enumerate() {
+        for (k, (gx, gy)) in ghost_positions.iter().enumerate() {
+            let g = &mut self.ghosts[k];
+
             // Animación simple del fantasma
-            if let Some(gs) = self.sprites.get_mut(*gi) {
-                gs.anim_time += dt;
-                if gs.anim_time > 0.3 {
-                    gs.anim_time = 0.0;
-                    gs.anim_frame = (gs.anim_frame + 1) % 2;
+            advance_anim(&mut g.anim_time, &mut g.anim_frame, dt, GHOST_FRAME_TIME);
+
+            match g.state {
+                GhostState::Eyes => {
+                    // Ojos: directo a casa atravesando paredes; allí esperan y reviven
+                    g.vel_x = 0.0;
+                    g.vel_y = 0.0;
+                    g.target_x = g.home_x;
+                    g.target_y = g.home_y;
+                    let (hx, hy) = (g.home_x - gx, g.home_y - gy);
+                    let d = (hx * hx + hy * hy).sqrt();
+                    let travel = EYES_SPEED * dt;
+                    if d > travel {
+                        g.x += hx / d * travel;
+                        g.y += hy / d * travel;
+                        g.heading = hy.atan2(hx);
+                    } else {
+                        g.x = g.home_x;
+                        g.y = g.home_y;
+                        g.state_timer -= dt;
+                        if g.state_timer <= 0.0 {
+                            g.state = GhostState::Normal;
+                        }
+                    }
+                    continue;
                 }
+                GhostState::Frightened => {
+                    g.state_timer -= dt;
+                    if g.state_timer <= 0.0 {
+                        g.state = GhostState::Normal;
+                    }
+                }
+                GhostState::Normal => {}
             }
+            let frightened = g.state == GhostState::Frightened;
 
-            // Objetivo desplazado en círculo alrededor del jugador (diferente por fantasma)
+            // Visión limitada: solo persigue si tiene al jugador en el cono (o lo vio hace poco)
+            let chasing = if self.settings.ghost_vision && !frightened && !rush {
+                if ghost_sees_player(&self.level, (*gx, *gy, g.heading), (self.player.x, self.player.y)) {
+                    g.alert = GHOST_ALERT_TIME;
+                } else {
+                    g.alert = (g.alert - dt).max(0.0);
+                }
+                g.alert > 0.0
+            } else {
+                true
+            };
+
+            // Objetivo desplazado en círculo alrededor del jugador (diferente por fantasma).
+            // Asustado: huye en sentido contrario al jugador. En estampida: directo a él.
+            // Sin ver al jugador: deambula hacia adelante con giros suaves.
             let angle = self.time * 0.6 + (k as f32) * 1.2566371; // ~2π/5
-            let target_x = self.player.x + angle.cos() * scatter_r;
-            let target_y = self.player.y + angle.sin() * scatter_r;
+            let (target_x, target_y) = if frightened {
+                (2.0 * gx - self.player.x, 2.0 * gy - self.player.y)
+            } else if rush {
+                (self.player.x, self.player.y)
+            } else if !chasing {
+                let wander = g.heading + (self.time * 0.7 + k as f32 * 2.1).sin() * 1.2;
+                (gx + wander.cos() * 3.0, gy + wander.sin() * 3.0)
+            } else {
+                (self.player.x + angle.cos() * scatter_r, self.player.y + angle.sin() * scatter_r)
+            };
+
+            g.target_x = target_x;
+            g.target_y = target_y;
 
             // Dirección hacia el objetivo
             let mut vx = target_x - gx;
@@ -395,7 +1828,7 @@ impl Game {
             // Fuerza de separación de otros fantasmas
             let mut repx = 0.0;
             let mut repy = 0.0;
-            for (j, (_oj_i, ox, oy)) in ghost_positions.iter().enumerate() {
+            for (j, (ox, oy)) in ghost_positions.iter().enumerate() {
                 if j == k {
                     continue;
                 }
@@ -420,88 +1853,404 @@ impl Game {
             len = (fx * fx + fy * fy).sqrt().max(1e-4);
             fx /= len;
             fy /= len;
+            g.heading = fy.atan2(fx);
+
+            // Movimiento con colisiones (más lento mientras está asustado). Sobre
+            // hielo patinan igual que el jugador: la velocidad sigue de a poco al rumbo.
+            let speed = if frightened { speed * 0.5 } else { speed } * g.speed;
+            let (cx, cy) = self.level.cell_at(g.x, g.y);
+            if self.level.is_ice(cx, cy) {
+                let k = (ICE_GRIP * dt).min(1.0);
+                g.vel_x += (fx * speed - g.vel_x) * k;
+                g.vel_y += (fy * speed - g.vel_y) * k;
+            } else {
+                g.vel_x = fx * speed;
+                g.vel_y = fy * speed;
+            }
+            let (nx, ny) = slide_move(&self.level, g.x, g.y, g.vel_x * dt, g.vel_y * dt, GHOST_RADIUS);
+            // Chocar con una pared corta la inercia en ese eje
+            g.vel_x = (nx - g.x) / dt;
+            g.vel_y = (ny - g.y) / dt;
+            g.x = nx;
+            g.y = ny;
+        }
+    }
+
+    // Dispara la estampida la primera vez que quedan RUSH_PELLETS monedas o menos
+    fn check_rush(&mut self) {
+        if self.rush_done || !self.settings.rush_event || self.ghosts.is_empty() {
+            return;
+        }
+        if self.pellets_remaining > 0 && self.pellets_remaining <= RUSH_PELLETS {
+            self.rush_done = true;
+            self.rush_time = RUSH_DURATION;
+            self.audio.play_sfx_ducked("assets/sfx/rush.wav");
+        }
+    }
 
-            // Movimiento con colisiones
-            let nx = gx + fx * speed * dt;
-            let ny = gy + fy * speed * dt;
+    // Marca la celda del jugador con el tiempo actual
+    fn update_trail(&mut self) {
+        let (x, y) = self.level.cell_at(self.player.x, self.player.y);
+        if x >= 0 && y >= 0 && x < self.level.w && y < self.level.h {
+            if let Some(t) = self.trail.get_mut((y * self.level.w + x) as usize) {
+                *t = self.time;
+            }
+        }
+    }
 
-            if let Some(gs) = self.sprites.get_mut(*gi) {
-                if !is_wall_level(&self.level, nx, gs.y) {
-                    gs.x = nx;
-                }
-                if !is_wall_level(&self.level, gs.x, ny) {
-                    gs.y = ny;
-                }
+    // Habitación (de la segmentación del nivel) que contiene el punto, si hay
+    fn room_at(&self, x: f32, y: f32) -> Option<usize> {
+        let (cx, cy) = self.level.cell_at(x, y);
+        if cx < 0 || cy < 0 || cx >= self.level.w || cy >= self.level.h {
+            return None;
+        }
+        let id = self.rooms.ids[(cy * self.level.w + cx) as usize];
+        (id >= 0).then_some(id as usize)
+    }
+
+    // Al entrar por primera vez a una habitación que todavía tiene monedas suena
+    // un aviso corto; las que se visitan vacías solo quedan marcadas
+    fn update_room_entry(&mut self) {
+        let Some(id) = self.room_at(self.player.x, self.player.y) else {
+            return;
+        };
+        if self.rooms_entered.get(id).is_none_or(|&entered| entered) {
+            return;
+        }
+        self.rooms_entered[id] = true;
+        if self.pellets.iter().any(|p| self.room_at(p.x, p.y) == Some(id)) {
+            self.audio.play_sfx("assets/sfx/room.wav");
+        }
+    }
+
+    // Anota dónde está cada fantasma que el jugador tiene a la vista
+    fn update_ghost_sightings(&mut self) {
+        self.ghost_sightings.resize(self.ghosts.len(), None);
+        let eye = (self.player.x, self.player.y);
+        for (g, seen) in self.ghosts.iter().zip(self.ghost_sightings.iter_mut()) {
+            if line_of_sight(&self.level, eye, (g.x, g.y)) {
+                *seen = Some(GhostSighting { x: g.x, y: g.y, time: self.time });
             }
         }
     }
 
+    // Dónde y de qué color va el fantasma `i` en el minimapa. Con memoria se usa
+    // el último avistamiento, cada vez más apagado; el radar muestra la posición real.
+    fn minimap_ghost_marker(&self, i: usize, g: &Ghost) -> Option<((f32, f32), [u8; 4])> {
+        let color = minimap_ghost_color(g, &self.render_opts.theme);
+        if !self.settings.minimap_memory || self.is_pinged(g) {
+            return Some(((g.x, g.y), color));
+        }
+        let seen = self.ghost_sightings.get(i).copied().flatten()?;
+        let fade = (self.time - seen.time) / GHOST_MEMORY_FADE;
+        if fade >= 1.0 {
+            return None;
+        }
+        let floor = minimap_tile_color(0, &self.render_opts.theme);
+        let mix = |c: u8, f: u8| (c as f32 + (f as f32 - c as f32) * fade) as u8;
+        let faded = [mix(color[0], floor[0]), mix(color[1], floor[1]), mix(color[2], floor[2]), 255];
+        Some(((seen.x, seen.y), faded))
+    }
+
     fn check_collisions_and_pickups(&mut self) {
         // 1) Recolección de pellets (pellets pequeños -> radio reducido)
         let pickup_r2 = 0.18f32 * 0.18f32;
 
-        let mut collected_indices = Vec::new();
-        for (i, s) in self.sprites.iter().enumerate() {
-            if s.kind == SpriteKind::Pellet {
-                let dx = self.player.x - s.x;
-                let dy = self.player.y - s.y;
-                let dist2 = dx * dx + dy * dy;
-                if dist2 < pickup_r2 {
-                    collected_indices.push(i);
-                }
-            }
-        }
-        if !collected_indices.is_empty() {
-            collected_indices.sort_unstable();
-            collected_indices.drain(..).rev().for_each(|i| {
-                self.sprites.remove(i);
-            });
-            let collected = collected_indices.len();
-            if collected > 0 {
-                if self.pellets_remaining >= collected {
-                    self.pellets_remaining -= collected;
-                } else {
-                    self.pellets_remaining = 0;
-                }
+        // Un solo barrido con retain sobre las monedas
+        let (px, py) = (self.player.x, self.player.y);
+        let mut picked = Vec::new();
+        self.pellets.retain(|s| {
+            let dx = px - s.x;
+            let dy = py - s.y;
+            if dx * dx + dy * dy < pickup_r2 {
+                picked.push((s.x, s.y, s.kind));
+                false
+            } else {
+                true
+            }
+        });
+        let collected = picked.len();
+        let mut power = false;
+        for &(x, y, kind) in &picked {
+            spawn_burst(&mut self.particles, x, y, 0.0, rgba(self.render_opts.theme.pellet, 255), 12);
+            power |= kind == SpriteKind::PowerPellet;
+            if kind == SpriteKind::FreezePellet {
+                self.freeze_time = FREEZE_TIME;
+            }
+        }
+        if power {
+            // Todos los fantasmas que no son ojos pasan a ser comestibles
+            for g in self.ghosts.iter_mut().filter(|g| g.state != GhostState::Eyes) {
+                g.state = GhostState::Frightened;
+                g.state_timer = FRIGHTENED_TIME;
+            }
+        }
+        if collected > 0 {
+            self.pellets_remaining = self.pellets_remaining.saturating_sub(collected);
+            if let Some(best) = self.campaign_collected.get_mut(self.level_index) {
+                *best = (*best).max(self.total_pellets - self.pellets_remaining);
+            }
+            for &(_, _, kind) in &picked {
+                self.combo_count += 1;
+                self.add_score(self.pellet_value(kind) * self.combo_multiplier());
+            }
+            self.combo_timer = COMBO_WINDOW;
+            if self.pellet_sfx_cooldown <= 0.0 {
                 self.audio.play_sfx("assets/sfx/pellet.wav");
+                self.pellet_sfx_cooldown = PELLET_SFX_INTERVAL;
             }
         }
 
-        // 2) Colisión con fantasmas -> pierde vida
-        if self.invincible_time <= 0.0 && self.mode == Mode::Playing {
+        // 2) Colisión con fantasmas -> pierde vida (no en práctica)
+        if self.invincible_time <= 0.0 && self.mode == Mode::Playing && !self.settings.practice {
             let hit_r2 = 0.30f32 * 0.30f32;
-            let mut hit = false;
-
-            for s in self.sprites.iter() {
-                if s.kind == SpriteKind::Ghost {
-                    let dx = self.player.x - s.x;
-                    let dy = self.player.y - s.y;
-                    let d2 = dx * dx + dy * dy;
-                    if d2 < hit_r2 {
-                        hit = true;
-                        break;
+            // Dirección del fantasma al jugador en el choque (para el empuje)
+            let mut hit: Option<(f32, f32)> = None;
+            let mut eaten = 0;
+
+            for g in self.ghosts.iter_mut() {
+                let dx = self.player.x - g.x;
+                let dy = self.player.y - g.y;
+                let d2 = dx * dx + dy * dy;
+                if d2 >= hit_r2 {
+                    continue;
+                }
+                match g.state {
+                    // Comido: vuelve a casa como ojos
+                    GhostState::Frightened => {
+                        g.state = GhostState::Eyes;
+                        g.state_timer = GHOST_RESPAWN_DELAY;
+                        eaten += 1;
+                        let color = ghost_color(&self.render_opts.theme, g.color_index, 0, 255);
+                        spawn_burst(&mut self.particles, g.x, g.y, 0.0, color, GHOST_BURST_PARTICLES);
                     }
+                    // Congelado no hace daño, salvo que se haya configurado lo contrario
+                    GhostState::Normal if self.freeze_time > 0.0 && !self.settings.freeze_lethal => {}
+                    // Mientras dura el aturdimiento el mismo contacto no vuelve a contar
+                    GhostState::Normal if self.stun_time > 0.0 => {}
+                    GhostState::Normal => hit = Some((dx, dy)),
+                    GhostState::Eyes => {}
                 }
             }
+            if eaten > 0 {
+                self.start_shake(0.2, 3.0);
+                self.add_score(GHOST_EAT_POINTS * eaten);
+                self.ghosts_eaten += eaten;
+                self.audio.play_sfx_ducked("assets/sfx/eat_ghost.wav");
+            }
 
-            if hit {
+            if let Some(normal) = hit.filter(|_| self.settings.gentle_bumps) {
+                self.bump_count += 1;
+                self.bump_memory = BUMP_MEMORY;
+                if self.bump_count < BUMP_LIMIT {
+                    self.bump_player(normal);
+                    hit = None;
+                } else {
+                    self.bump_count = 0;
+                }
+            }
+            if hit.is_some() {
+                self.stun_time = 0.0;
                 self.lives -= 1;
-                self.audio.play_sfx("assets/sfx/hit.wav");
+                self.audio.play_sfx_ducked("assets/sfx/hit.wav");
+                self.start_shake(0.35, 6.0);
 
+                // Sin vidas, step() pasa a Game Over vía check_loss
                 if self.lives > 0 {
-                    // Respawn con invulnerabilidad
-                    let (px, py) = self.level.spawn;
-                    self.player.x = px as f32 + 0.5;
-                    self.player.y = py as f32 + 0.5;
-                    self.invincible_time = 2.0;
-                } else {
-                    // Game Over
-                    self.mode = Mode::GameOver;
-                    self.death_anim_t = 0.0;
-                    self.audio.play_sfx("assets/sfx/game_over.wav");
+                    // Respawn con invulnerabilidad según la dificultad
+                    let (px, py) = self.respawn_position();
+                    self.player.x = px;
+                    self.player.y = py;
+                    self.player.vel_x = 0.0;
+                    self.player.vel_y = 0.0;
+                    self.grid.active = false;
+                    self.sync_prev_positions();
+                    self.invincible_time = self.settings.difficulty.invuln_duration();
+                }
+            }
+        }
+    }
+
+    // Choque suave: empuja al jugador lejos del fantasma por la normal de contacto
+    // (o hacia atrás si están encimados) y lo aturde un momento
+    fn bump_player(&mut self, (dx, dy): (f32, f32)) {
+        let len = (dx * dx + dy * dy).sqrt();
+        let (nx, ny) = if len > 1e-4 {
+            (dx / len, dy / len)
+        } else {
+            (-self.player.dir_x, -self.player.dir_y)
+        };
+        self.knockback = (nx * BUMP_SPEED, ny * BUMP_SPEED);
+        self.stun_time = BUMP_STUN_TIME;
+        self.player.vel_x = 0.0;
+        self.player.vel_y = 0.0;
+        self.grid.active = false;
+        self.audio.play_sfx("assets/sfx/bump.wav");
+        self.start_shake(0.15, 2.0);
+    }
+
+    // Aturdido: el jugador no maneja y se desliza con el empuje, que se frena solo
+    fn update_stun(&mut self, dt: f32) {
+        self.stun_time = (self.stun_time - dt).max(0.0);
+        let (kx, ky) = self.knockback;
+        let p = &mut self.player;
+        (p.x, p.y) = slide_move(&self.level, p.x, p.y, kx * dt, ky * dt, PLAYER_RADIUS);
+        let drag = (-BUMP_DRAG * dt).exp();
+        self.knockback = (kx * drag, ky * drag);
+    }
+
+    // Asomarse con Z/C (o Q/E en controles modernos, donde no giran): la
+    // cámara se corre de costado sin girar y vuelve sola al soltar
+    fn update_lean(&mut self, dt: f32) {
+        let tank = self.control_scheme == ControlScheme::Tank;
+        let left = self.is_down(VirtualKeyCode::Z) || (!tank && self.is_down(VirtualKeyCode::Q));
+        let right = self.is_down(VirtualKeyCode::C) || (!tank && self.is_down(VirtualKeyCode::E));
+        let target = match (left, right) {
+            _ if self.settings.grid_movement => 0.0,
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        self.lean += (target - self.lean) * (LEAN_SPEED * dt).min(1.0);
+        if self.lean.abs() < 1e-3 && target == 0.0 {
+            self.lean = 0.0;
+        }
+    }
+
+    // Desplazamiento de la cámara al asomarse desde (x, y), a lo largo del plano de
+    // cámara. Se acorta si la cámara corrida quedaría dentro de una pared.
+    fn lean_offset(&self, x: f32, y: f32) -> (f32, f32) {
+        if self.lean == 0.0 || self.settings.lean_distance <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let p = &self.player;
+        let plane_len = (p.plane_x * p.plane_x + p.plane_y * p.plane_y).sqrt().max(1e-6);
+        let d = self.lean * self.settings.lean_distance;
+        let (rx, ry) = (p.plane_x / plane_len * d, p.plane_y / plane_len * d);
+        let mut scale = 1.0;
+        while scale > 0.0 && !can_move_to(&self.level, x + rx * scale, y + ry * scale, LEAN_CLEARANCE) {
+            scale -= 0.25;
+        }
+        let scale = scale.max(0.0);
+        (rx * scale, ry * scale)
+    }
+
+    // Piso de lava bajo el jugador: acumula daño y quita una vida al completarse
+    // (sin reaparecer en otro lado: el respiro alcanza para salir caminando)
+    fn update_hazard(&mut self, dt: f32) {
+        self.hazard_cooldown = (self.hazard_cooldown - dt).max(0.0);
+        let (cx, cy) = self.level.cell_at(self.player.x, self.player.y);
+        if !self.level.is_hazard(cx, cy) || self.settings.practice {
+            self.hazard_damage = (self.hazard_damage - HAZARD_RECOVER_RATE * dt).max(0.0);
+            return;
+        }
+        if self.hazard_cooldown > 0.0 {
+            return;
+        }
+        self.hazard_damage += HAZARD_DAMAGE_RATE * dt;
+        if self.hazard_damage >= 1.0 {
+            self.hazard_damage = 0.0;
+            self.hazard_cooldown = HAZARD_COOLDOWN;
+            self.lives -= 1;
+            self.audio.play_sfx_ducked("assets/sfx/hurt.wav");
+            self.start_shake(0.25, 4.0);
+        }
+    }
+
+    // Dónde reaparecer tras un golpe: el spawn del nivel o, si se pidió, el mismo
+    // lugar alejándose del fantasma que golpeó para no recibir otro golpe al instante.
+    fn respawn_position(&self) -> (f32, f32) {
+        let (sx, sy) = self.level.spawn;
+        let spawn = self.level.cell_center(sx, sy);
+        if !self.settings.respawn_in_place {
+            return spawn;
+        }
+
+        let clear_r2 = 0.8f32 * 0.8f32;
+        let is_clear = |x: f32, y: f32| {
+            can_move_to(&self.level, x, y, PLAYER_RADIUS)
+                && self.ghosts.iter().all(|g| {
+                    g.state != GhostState::Normal || (g.x - x).powi(2) + (g.y - y).powi(2) >= clear_r2
+                })
+        };
+
+        let (px, py) = (self.player.x, self.player.y);
+        // Alejarse del fantasma más cercano; probar otras direcciones si hay pared
+        let nearest = self.ghosts.iter().filter(|g| g.state == GhostState::Normal).min_by(|a, b| {
+            let da = (a.x - px).powi(2) + (a.y - py).powi(2);
+            let db = (b.x - px).powi(2) + (b.y - py).powi(2);
+            da.partial_cmp(&db).unwrap()
+        });
+        let away = nearest.map_or(0.0, |g| (py - g.y).atan2(px - g.x));
+        for step in 1..=6 {
+            let dist = step as f32 * 0.25;
+            for turn in 0..8 {
+                let a = away + turn as f32 * std::f32::consts::FRAC_PI_4;
+                let (x, y) = (px + a.cos() * dist, py + a.sin() * dist);
+                if is_clear(x, y) {
+                    return (x, y);
                 }
             }
         }
+        spawn
+    }
+
+    // Vibración de cámara; la amplitud se escala con la opción del menú
+    fn start_shake(&mut self, duration: f32, intensity: f32) {
+        self.shake_time = duration;
+        self.shake_duration = duration;
+        self.shake_intensity = intensity * self.settings.shake_strength;
+    }
+
+    // Desplazamiento aleatorio de la escena, decae suave (cuadrático) a cero
+    fn shake_offset(&self) -> (i32, i32) {
+        if self.shake_time <= 0.0 || self.shake_intensity <= 0.0 {
+            return (0, 0);
+        }
+        let k = self.shake_time / self.shake_duration;
+        let amp = self.shake_intensity * k * k;
+        let mut rng = rand::thread_rng();
+        (rng.gen_range(-amp..=amp).round() as i32, rng.gen_range(-amp..=amp).round() as i32)
+    }
+
+    // Victoria: juntar la fracción de monedas que pide el nivel (todas por defecto)
+    pub fn check_win(&self) -> bool {
+        let collected = self.total_pellets.saturating_sub(self.pellets_remaining);
+        collected >= self.level.required_pellets(self.total_pellets)
+    }
+
+    // Derrota: sin vidas o, en contrarreloj, sin tiempo. En práctica no se pierde.
+    pub fn check_loss(&self) -> bool {
+        if self.settings.practice {
+            return false;
+        }
+        self.lives <= 0 || self.time_left.is_some_and(|t| t <= 0.0)
+    }
+
+    // Puntos de cada tipo de moneda; el nivel puede cambiar el valor de las chicas
+    fn pellet_value(&self, kind: SpriteKind) -> u32 {
+        match kind {
+            SpriteKind::BigDot => BIG_DOT_POINTS,
+            _ => self.level.pellet_points.unwrap_or(PELLET_POINTS),
+        }
+    }
+
+    // Suma puntos y otorga vidas extra al cruzar cada umbral
+    fn add_score(&mut self, points: u32) {
+        self.score += points;
+        while self.score >= self.next_extra_life {
+            self.next_extra_life += EXTRA_LIFE_EVERY;
+            if self.lives < MAX_LIVES {
+                self.lives += 1;
+                self.extra_life_flash = 1.5;
+                self.audio.play_sfx_ducked("assets/sfx/extra_life.wav");
+            }
+        }
+    }
+
+    // x1 al empezar; sube con cada moneda encadenada hasta MAX_COMBO
+    fn combo_multiplier(&self) -> u32 {
+        self.combo_count.clamp(1, MAX_COMBO)
     }
 
     fn is_down(&self, key: VirtualKeyCode) -> bool {
@@ -511,29 +2260,138 @@ impl Game {
     pub fn render(&mut self, frame: &mut [u8], w: i32, h: i32) {
         match self.mode {
             Mode::Menu => self.render_menu(frame, w, h),
+            Mode::Settings => self.render_settings(frame, w, h),
             Mode::Playing => self.render_game(frame, w, h),
             Mode::Paused => self.render_paused(frame, w, h),
+            Mode::WinSequence => self.render_win_sequence(frame, w, h),
             Mode::Win => self.render_win(frame, w, h),
             Mode::GameOver => self.render_game_over(frame, w, h),
+            Mode::LevelPreview => self.render_level_preview(frame, w, h),
+            Mode::Error(_) => self.render_error(frame, w, h),
+        }
+        if self.help_visible {
+            self.render_help(frame, w, h);
+        }
+        apply_crt(frame, w, h, self.settings.crt_intensity);
+    }
+
+    // Punto rojo "REC" en la esquina; main lo dibuja después de capturar el
+    // cuadro para que no aparezca en la grabación
+    pub fn render_rec_indicator(&self, frame: &mut [u8], w: i32, h: i32) {
+        if !self.recording {
+            return;
+        }
+        rect_fill(frame, w, h, w - 46, 8, 8, 8, [230, 20, 20, 255]);
+        draw_text_small(frame, w, h, w - 34, 7, "REC", [230, 20, 20, 255]);
+    }
+
+    // Overlay de ayuda: controles y objetivo sobre la escena oscurecida
+    fn render_help(&self, frame: &mut [u8], w: i32, h: i32) {
+        rect_fill(frame, w, h, 0, 0, w, h, [0, 0, 0, 160]);
+        let lines = [
+            "AYUDA",
+            "",
+            "Objetivo: recoge todas las monedas sin que te toquen los fantasmas.",
+            "Los power pellets rosados vuelven comestibles a los fantasmas.",
+            "El cristal celeste los congela unos segundos. Las monedas grandes valen 50.",
+            "El piso de lava quema: si te quedas encima pierdes una vida.",
+            "",
+            "W S: avanzar y retroceder",
+            "Q E o Flechas: girar   A D: lateral en controles modernos",
+            "Mouse: girar   Clic: usar   Espacio: radar   Z C: asomarse",
+            "P: pausa   L: leyenda de fantasmas   O en el menu: opciones",
+            "Inicio: reiniciar la camara",
+            "V: vista en primera persona o desde arriba",
+            "F9: grabar cuadros en captures",
+            "F2: soltar o capturar el mouse",
+            "H o Esc: cerrar esta ayuda",
+        ];
+        let y0 = h / 2 - lines.len() as i32 * 7;
+        for (i, txt) in lines.iter().enumerate() {
+            let color = if i == 0 { [255, 230, 0, 255] } else { [230, 230, 230, 255] };
+            draw_text_small(frame, w, h, 40, y0 + i as i32 * 14, txt, color);
         }
     }
 
     fn render_menu(&mut self, frame: &mut [u8], w: i32, h: i32) {
-        fill(frame, w, h, 0x10, 0x10, 0x18);
+        if self.attract {
+            // Demo de fondo, oscurecida para que el texto se lea
+            let (cam, ghosts) = self.interpolated_view();
+            self.render_world(frame, w, h, &cam, &ghosts);
+            dim_frame(frame, 0.35);
+        } else {
+            fill(frame, w, h, 0x10, 0x10, 0x18);
+        }
         draw_text_small(frame, w, h, 16, 16, "PACMAN 3D - Raycaster", [255, 230, 0, 255]);
         draw_text_small(frame, w, h, 16, 40, "Selecciona un nivel:", [200, 200, 200, 255]);
         draw_text_small(frame, w, h, 16, 60, "[1] Nivel 1", [180, 220, 255, 255]);
         draw_text_small(frame, w, h, 16, 75, "[2] Nivel 2", [180, 220, 255, 255]);
         draw_text_small(frame, w, h, 16, 90, "[3] Nivel 3", [180, 220, 255, 255]);
+        draw_text_small(frame, w, h, 16, 105, "[O] Opciones", [180, 220, 255, 255]);
+        let controls = match self.control_scheme {
+            ControlScheme::Tank => "Controles: W/S mover, Q/E o Flechas rotar, Mouse rota, P pausar",
+            ControlScheme::Modern => "Controles: W/S mover, A/D lateral, Mouse o Flechas rotar, P pausar",
+        };
+        draw_text_small(frame, w, h, 16, 120, controls, [180, 180, 180, 255]);
+        draw_text_small(frame, w, h, 16, 135, "[H] Ayuda", [180, 220, 255, 255]);
+        draw_text_small(frame, w, h, 16, 150, "[0] Tutorial", [180, 220, 255, 255]);
+        self.render_campaign_progress(frame, w, h, 175);
+    }
+
+    fn render_settings(&mut self, frame: &mut [u8], w: i32, h: i32) {
+        fill(frame, w, h, 0x10, 0x10, 0x18);
+        draw_text_small(frame, w, h, 16, 16, "OPCIONES", [255, 230, 0, 255]);
+        let rows = settings_rows(h);
+        let visible = scroll_window(self.settings_cursor, Settings::ITEM_COUNT, rows);
+        let dim = [120, 120, 120, 255];
+        if visible.start > 0 {
+            draw_text_small(frame, w, h, 16, 28, "- mas arriba -", dim);
+        }
+        if visible.end < Settings::ITEM_COUNT {
+            draw_text_small(frame, w, h, 16, 40 + rows as i32 * SETTINGS_ROW_H, "- mas abajo -", dim);
+        }
+        for (row, i) in visible.enumerate() {
+            let y = 40 + row as i32 * SETTINGS_ROW_H;
+            let color = if i == self.settings_cursor {
+                rect_fill(frame, w, h, 8, y + 1, 4, 4, [255, 230, 0, 255]);
+                [255, 255, 255, 255]
+            } else {
+                [180, 180, 180, 255]
+            };
+            draw_text_small(frame, w, h, 16, y, &self.settings.item_label(i), color);
+        }
         draw_text_small(
             frame,
             w,
             h,
             16,
-            120,
-            "Controles: W/S mover, Q/E o Flechas rotar, Mouse rota, P pausar",
+            h - 20,
+            "Arriba/Abajo elegir, Izq/Der cambiar, Enter volver",
             [180, 180, 180, 255],
         );
+        let music = match self.audio.music_status {
+            // Sin archivos de música el juego simplemente va en silencio
+            MusicStatus::Playing | MusicStatus::Missing => None,
+            MusicStatus::Fallback => Some("Musica: no se pudo cargar, suena el tema por defecto"),
+            MusicStatus::Failed => Some("Musica: no se pudo cargar"),
+            MusicStatus::NoDevice => Some("Audio: no hay dispositivo de salida"),
+        };
+        if let Some(txt) = music {
+            draw_text_small(frame, w, h, 16, h - 36, txt, rgba(self.render_opts.theme.hud_danger, 255));
+        }
+    }
+
+    // Escena girando sin HUD, con el cartel de victoria encima
+    fn render_win_sequence(&mut self, frame: &mut [u8], w: i32, h: i32) {
+        let (cam, ghosts) = self.interpolated_view();
+        self.render_world(frame, w, h, &cam, &ghosts);
+        let k = ui_scale(h);
+        let title = "Nivel completado!";
+        let (tw, _) = text_size(title, 2 * k);
+        draw_text_scaled(frame, w, h, w / 2 - tw / 2, h / 3, title, [255, 230, 0, 255], 2 * k);
+        let hint = "Cualquier tecla: continuar";
+        let (hw, _) = text_size(hint, k);
+        draw_text_scaled(frame, w, h, w / 2 - hw / 2, h / 3 + 24 * k, hint, [220, 220, 220, 255], k);
     }
 
     fn render_win(&mut self, frame: &mut [u8], w: i32, h: i32) {
@@ -548,38 +2406,223 @@ impl Game {
             "Presiona Enter para volver al menu",
             [200, 200, 200, 255],
         );
+
+        // Resumen del nivel; los récords nuevos se marcan en amarillo
+        let mut y = 64;
+        if let Some(s) = &self.last_stats {
+            let secs = s.time as i32;
+            let record = [255, 230, 0, 255];
+            let plain = [220, 220, 220, 255];
+            let lines = [
+                (format!("Monedas: {}/{}", s.collected, s.total), false),
+                (format!("Tiempo: {}:{:02}", secs / 60, secs % 60), s.new_best_time),
+                (format!("Fantasmas comidos: {}", s.ghosts_eaten), false),
+                (format!("Puntos: {}", s.score), s.new_best_score),
+            ];
+            for (txt, best) in &lines {
+                draw_text_small(frame, w, h, 16, y, txt, if *best { record } else { plain });
+                if *best {
+                    draw_text_small(frame, w, h, 16 + (txt.len() as i32 + 2) * 6, y, "Nuevo record!", record);
+                }
+                y += 14;
+            }
+            y += 10;
+        }
+        self.render_campaign_progress(frame, w, h, y);
     }
 
-    fn render_game(&mut self, frame: &mut [u8], w: i32, h: i32) {
-        render_scene(frame, w, h, &self.level, &self.player, &self.sprites, &mut self.depth);
+    fn render_campaign_progress(&self, frame: &mut [u8], w: i32, h: i32, y: i32) {
+        let (collected, total) = self.campaign_progress();
+        let txt = format!("Monedas en todos los niveles: {}/{}", collected, total);
+        draw_text_small(frame, w, h, 16, y, &txt, rgba(self.render_opts.theme.pellet, 255));
+    }
 
-        // HUD
+    // Escena 3D + partículas. Con antialiasing se dibuja al doble de resolución
+    // y se reduce; el HUD va después a resolución normal para que el texto quede nítido.
+    fn render_world(&mut self, frame: &mut [u8], w: i32, h: i32, cam: &Player, ghosts: &[Ghost]) {
+        let ss = self.settings.supersampling();
+        let (rw, rh) = if ss { (w * 2, h * 2) } else { (w, h) };
+        self.depth.resize(rw as usize);
+
+        let mut ss_frame = std::mem::take(&mut self.ss_frame);
+        let target: &mut [u8] = if ss {
+            ss_frame.resize((rw * rh * 4) as usize, 0);
+            &mut ss_frame
+        } else {
+            frame
+        };
+        render_scene_behind_glass(
+            target,
+            rw,
+            rh,
+            &self.level,
+            cam,
+            &self.pellets,
+            ghosts,
+            &mut self.depth,
+            &self.render_opts,
+        );
+        if !self.render_opts.depth_view {
+            draw_particles(target, rw, rh, cam, &self.particles, &self.depth);
+            draw_translucent_layers(target, rw, rh, cam, &self.depth);
+        }
+        if ss {
+            downsample_2x(&ss_frame, frame, w, h);
+        }
+        self.ss_frame = ss_frame;
+    }
+
+    fn render_game(&mut self, frame: &mut [u8], w: i32, h: i32) {
+        let (cam, ghosts) = self.interpolated_view();
+        match self.view_mode {
+            ViewMode::FirstPerson => self.render_world(frame, w, h, &cam, &ghosts),
+            ViewMode::TopDown => self.render_top_down(frame, w, h, &cam, &ghosts),
+        }
+        let (sx, sy) = self.shake_offset();
+        shift_frame(frame, w, h, sx, sy);
+
+        // HUD: coordenadas pensadas para 400 px de alto, multiplicadas por ui_scale
+        let k = ui_scale(h);
+        let backing = self.settings.hud_backing;
+        let hud = |frame: &mut [u8], x: i32, y: i32, txt: &str, color: [u8; 4]| {
+            if backing {
+                text_backing(frame, w, h, x * k, y * k, txt, k);
+            }
+            draw_text_scaled(frame, w, h, x * k, y * k, txt, color, k)
+        };
+        let text = rgba(self.render_opts.theme.hud_text, 255);
         let fps_txt = format!("FPS: {:.0}", self.fps);
-        draw_text_small(frame, w, h, 6, 6, &fps_txt, [255, 255, 255, 255]);
+        hud(frame, 6, 6, &fps_txt, text);
 
         // Monedas (recogidas / total) y faltantes
         let collected = self.total_pellets.saturating_sub(self.pellets_remaining);
         let coins_txt = format!("Monedas: {}/{}", collected, self.total_pellets);
-        draw_text_small(frame, w, h, 6, 20, &coins_txt, [255, 230, 0, 255]);
+        hud(frame, 6, 20, &coins_txt, rgba(self.render_opts.theme.pellet, 255));
 
-        let left_txt = format!("Faltan: {}", self.pellets_remaining);
-        draw_text_small(frame, w, h, 6, 34, &left_txt, [200, 200, 200, 255]);
+        // Cuántas faltan para ganar; si el nivel no pide todas, también la meta
+        let required = self.level.required_pellets(self.total_pellets);
+        let mut left_txt = format!("Faltan: {}", required.saturating_sub(collected));
+        if self.level.required_fraction < 1.0 {
+            left_txt += &format!("  Meta: {:.0}%", self.level.required_fraction * 100.0);
+        }
+        hud(frame, 6, 34, &left_txt, rgba(self.render_opts.theme.hud_dim, 255));
+
+        // Nombre del nivel centrado bajo el reloj
+        let name_w = self.level.name.len() as i32 * 6 * k;
+        if backing {
+            text_backing(frame, w, h, w / 2 - name_w / 2, 26 * k, self.level.name, k);
+        }
+        draw_text_scaled(frame, w, h, w / 2 - name_w / 2, 26 * k, self.level.name, text, k);
 
         // Vidas
         let lives_txt = format!("Vidas: {}", self.lives.max(0));
-        draw_text_small(frame, w, h, 6, 50, &lives_txt, [255, 100, 100, 255]);
-        for i in 0..self.lives.max(0) {
-            rect_fill(frame, w, h, 70 + i * 8, 50, 6, 6, [220, 40, 40, 255]);
+        let danger = rgba(self.render_opts.theme.hud_danger, 255);
+        hud(frame, 6, 50, &lives_txt, danger);
+        // Íconos en filas de 5 para cuando hay vidas extra
+        for i in 0..self.lives.clamp(0, MAX_LIVES) {
+            rect_fill(frame, w, h, (70 + (i % 5) * 8) * k, (50 + (i / 5) * 7) * k, 6 * k, 6 * k, danger);
+        }
+        if self.extra_life_flash > 0.0 && (self.extra_life_flash * 8.0) as i32 % 2 == 0 {
+            hud(frame, 116, 50, "+1 VIDA!", [120, 255, 120, 255]);
         }
+        if self.aggression_flash > 0.0 && (self.aggression_flash * 4.0) as i32 % 2 == 0 {
+            hud(frame, 116, 64, "Los fantasmas aceleran!", [255, 140, 60, 255]);
+        }
+        if self.freeze_time > 0.0 {
+            let freeze_txt = format!("Congelados: {:.0}", self.freeze_time.ceil());
+            hud(frame, 116, 78, &freeze_txt, rgba(self.render_opts.theme.frozen, 255));
+        }
+        if self.rush_time > 0.0 && (self.rush_time * 4.0) as i32 % 2 == 0 {
+            hud(frame, 116, 92, "ESTAMPIDA! Vienen todos", danger);
+        }
+        if self.debug_paused {
+            hud(frame, 116, 106, "STEP MODE  F6: seguir  F7: un paso", [120, 255, 120, 255]);
+        }
+        if self.view_reset_flash > 0.0 {
+            hud(frame, 116, 120, "Vista reiniciada", [120, 255, 120, 255]);
+        }
+
+        // Tiempo restante (contrarreloj), grande y centrado arriba
+        if let Some(t) = self.time_left {
+            let secs = t.ceil() as i32;
+            let txt = format!("{}:{:02}", secs / 60, secs % 60);
+            let color = if t < 10.0 { danger } else { text };
+            let tw = txt.len() as i32 * 6 * 2 * k;
+            if backing {
+                text_backing(frame, w, h, w / 2 - tw / 2, 6 * k, &txt, 2 * k);
+            }
+            draw_text_scaled(frame, w, h, w / 2 - tw / 2, 6 * k, &txt, color, 2 * k);
+        }
+
+        // Puntaje y combo
+        let score_txt = format!("Puntos: {}", self.score);
+        hud(frame, 6, 64, &score_txt, text);
+        if self.combo_multiplier() > 1 {
+            let combo_txt = format!("Combo x{}", self.combo_multiplier());
+            hud(frame, 6, 78, &combo_txt, [255, 160, 40, 255]);
+        }
+        if self.settings.practice {
+            hud(frame, 6, 92, "PRACTICA", [120, 200, 255, 255]);
+        }
+        // Quemadura sobre lava: barra que se llena hasta perder una vida
+        if self.hazard_damage > 0.0 {
+            hud(frame, 6, 120, "Quema", danger);
+            rect_fill(frame, w, h, 42 * k, 120 * k, 60 * k, 7 * k, [60, 20, 10, 255]);
+            let fill = (60.0 * self.hazard_damage.min(1.0)) as i32;
+            rect_fill(frame, w, h, 42 * k, 120 * k, fill * k, 7 * k, [255, 110, 20, 255]);
+        }
+
+        // Radar: usos restantes, atenuado mientras recarga
+        let ping_txt = format!("Radar: {}", self.ping_charges);
+        let ping_color = if self.ping_charges > 0 && self.ping_cooldown <= 0.0 {
+            [120, 255, 160, 255]
+        } else {
+            rgba(self.render_opts.theme.hud_dim, 160)
+        };
+        hud(frame, 6, 106, &ping_txt, ping_color);
 
-        // Efecto de invulnerabilidad (flash sutil)
+        // Efecto de invulnerabilidad (flash sutil), más rápido cuando está por terminar
         if self.invincible_time > 0.0 {
-            let a = ((self.invincible_time * 10.0).sin().abs() * 60.0) as u8;
+            let a = (invuln_blink_phase(self.invincible_time).sin().abs() * 60.0) as u8;
             rect_fill(frame, w, h, 0, 0, w, h, [255, 255, 255, a]);
         }
 
-        // Minimap
-        self.render_minimap(frame, w, h);
+        // Minimap (desde arriba ya se ve el mapa entero alrededor)
+        if self.view_mode == ViewMode::FirstPerson {
+            self.render_minimap(frame, w, h);
+        }
+
+        if let Some(step) = self.tutorial_step {
+            let prompt = TUTORIAL_PROMPTS[step.min(TUTORIAL_PROMPTS.len() - 1)];
+            rect_fill(frame, w, h, 0, h - 44 * k, w, 36 * k, [0, 0, 0, 160]);
+            draw_text_scaled(frame, w, h, 16 * k, h - 40 * k, prompt, [255, 230, 0, 255], k);
+            draw_text_scaled(frame, w, h, 16 * k, h - 24 * k, "Tab: saltar tutorial", [180, 180, 180, 255], k);
+        }
+
+        if self.debug {
+            self.render_debug_overlay(frame, w, h);
+        }
+    }
+
+    fn render_debug_overlay(&self, frame: &mut [u8], w: i32, h: i32) {
+        let t = &self.ghost_tuning;
+        let lines = [
+            format!(
+                "DEBUG  nivel: {}  fantasmas: {}  fps instantaneo: {:.0}",
+                self.level_index + 1,
+                self.ghosts.len(),
+                self.fps_raw
+            ),
+            format!("vel: {:.2}  dispersion: {:.1}  separacion: {:.1}", t.speed, t.scatter_r, t.sep_r),
+            "Teclas: [ ] vel   , . dispersion   ; ' separacion   - = fantasmas".to_string(),
+            "RePag/AvPag: nivel anterior/siguiente   F3: profundidad   F4: objetivos   F5: rayos".to_string(),
+            format!("F6: paso a paso   F7: avanzar un paso   F8: paredes ({})", self.wall_palette.label()),
+        ];
+        let k = ui_scale(h);
+        let y0 = h - (8 + lines.len() as i32 * 12) * k;
+        for (i, txt) in lines.iter().enumerate() {
+            draw_text_scaled(frame, w, h, 6 * k, y0 + i as i32 * 12 * k, txt, [120, 255, 120, 255], k);
+        }
     }
 
     fn render_paused(&mut self, frame: &mut [u8], w: i32, h: i32) {
@@ -600,6 +2643,14 @@ impl Game {
     }
 
     fn render_game_over(&mut self, frame: &mut [u8], w: i32, h: i32) {
+        if self.spectating {
+            let (cam, ghosts) = self.interpolated_view();
+            self.render_world(frame, w, h, &cam, &ghosts);
+            draw_text_small(frame, w, h, 16, 16, "GAME OVER - Modo espectador", [255, 255, 255, 255]);
+            draw_text_small(frame, w, h, 16, 30, "R: reintentar   Enter: menu", [220, 220, 220, 255]);
+            return;
+        }
+
         // Fondo oscuro
         fill(frame, w, h, 10, 0, 0);
 
@@ -608,16 +2659,149 @@ impl Game {
         let alpha = (t * 220.0) as u8;
         rect_fill(frame, w, h, 0, 0, w, h, [0, 0, 0, alpha]);
 
-        draw_text_small(frame, w, h, 16, 16, "GAME OVER", [255, 255, 255, 255]);
+        if self.time_up {
+            draw_text_small(frame, w, h, 16, 16, "TIEMPO AGOTADO", rgba(self.render_opts.theme.hud_danger, 255));
+        } else {
+            draw_text_small(frame, w, h, 16, 16, "GAME OVER", [255, 255, 255, 255]);
+        }
         draw_text_small(frame, w, h, 16, 40, "Presiona R para reintentar", [200, 200, 200, 255]);
         draw_text_small(frame, w, h, 16, 55, "Presiona Enter para menu", [200, 200, 200, 255]);
     }
 
+    fn render_error(&self, frame: &mut [u8], w: i32, h: i32) {
+        let Mode::Error(message) = &self.mode else {
+            return;
+        };
+        fill(frame, w, h, 0x18, 0x08, 0x08);
+        draw_text_small(frame, w, h, 16, 16, "No se pudo cargar el nivel", [255, 90, 90, 255]);
+        let mut y = 40;
+        for row in wrap_text(message, ((w - 32) / 6).max(1) as usize) {
+            draw_text_small(frame, w, h, 16, y, &row, [230, 230, 230, 255]);
+            y += 12;
+        }
+        let hint = [
+            "Revisa el archivo de --level: necesita un mapa",
+            "con exactamente una P para el jugador.",
+            "",
+            "Enter: jugar los niveles incluidos   Esc: salir",
+        ];
+        for txt in hint {
+            y += 12;
+            draw_text_small(frame, w, h, 16, y, txt, [200, 200, 200, 255]);
+        }
+    }
+
+    // Vista desde arriba: el minimapa (norte arriba) ampliado a toda la pantalla
+    // y centrado en la cámara interpolada, así el desplazamiento es suave
+    fn render_top_down(&self, frame: &mut [u8], w: i32, h: i32, cam: &Player, ghosts: &[Ghost]) {
+        fill(frame, w, h, 0, 0, 0);
+        let k = ui_scale(h);
+        let cell = TOP_DOWN_CELL_PX * k;
+        let unit = cell as f32 / self.level.tile_scale;
+        let to_screen = |x: f32, y: f32| {
+            (w / 2 + ((x - cam.x) * unit).round() as i32, h / 2 + ((y - cam.y) * unit).round() as i32)
+        };
+
+        // Solo las celdas que entran en pantalla
+        let half = (w.max(h) as f32 / unit) * 0.5 + self.level.tile_scale;
+        let (x0, y0) = self.level.cell_at(cam.x - half, cam.y - half);
+        let (x1, y1) = self.level.cell_at(cam.x + half, cam.y + half);
+        let pending = self.pending_rooms();
+        for cy in y0.max(0)..=y1.min(self.level.h - 1) {
+            for cx in x0.max(0)..=x1.min(self.level.w - 1) {
+                let corner = (cx as f32 * self.level.tile_scale, cy as f32 * self.level.tile_scale);
+                let (sx, sy) = to_screen(corner.0, corner.1);
+                let color = self.minimap_cell_color(cx, cy, &pending);
+                rect_fill(frame, w, h, sx, sy, cell, cell, color);
+            }
+        }
+
+        for s in &self.pellets {
+            self.draw_minimap_pellet(frame, w, h, s.kind, to_screen(s.x, s.y), (cell / 8).max(1));
+        }
+        for g in ghosts {
+            let (gx, gy) = to_screen(g.x, g.y);
+            let color = minimap_ghost_color(g, &self.render_opts.theme);
+            fill_disc(frame, w, h, (gx, gy), GHOST_RADIUS * unit, color);
+        }
+
+        let clip = (0, 0, w, h);
+        self.draw_ping_ring(frame, w, h, (w as f32 / 2.0, h as f32 / 2.0), unit, clip);
+        let marker = rgba(self.render_opts.theme.player_marker, 255);
+        fill_disc(frame, w, h, (w / 2, h / 2), PLAYER_RADIUS * unit, marker);
+        let (ex, ey) = to_screen(cam.x + cam.dir_x * 0.5, cam.y + cam.dir_y * 0.5);
+        line(frame, w, h, w / 2, h / 2, ex, ey, [255, 255, 255, 255]);
+    }
+
+    // Vista previa: el minimapa ampliado a casi toda la pantalla, con el inicio,
+    // las monedas y las casas de los fantasmas, más el conteo de cada cosa
+    fn render_level_preview(&self, frame: &mut [u8], w: i32, h: i32) {
+        fill(frame, w, h, 0x10, 0x10, 0x18);
+        let k = ui_scale(h);
+        let (top, bottom) = (36 * k, 40 * k);
+        let scale = ((w - 32) / self.level.w).min((h - top - bottom) / self.level.h).max(1);
+        let unit = scale as f32 / self.level.tile_scale;
+        let (map_w, map_h) = (self.level.w * scale, self.level.h * scale);
+        let origin_x = (w - map_w) / 2;
+        let origin_y = top + (h - top - bottom - map_h) / 2;
+        draw_text_scaled(frame, w, h, 16, 12 * k, self.level.name, [255, 230, 0, 255], k);
+
+        let pending = self.pending_rooms();
+        for y in 0..self.level.h {
+            for x in 0..self.level.w {
+                let color = self.minimap_cell_color(x, y, &pending);
+                rect_fill(frame, w, h, origin_x + x * scale, origin_y + y * scale, scale, scale, color);
+            }
+        }
+        let to_screen = |x: f32, y: f32| {
+            ((origin_x as f32 + x * unit) as i32, (origin_y as f32 + y * unit) as i32)
+        };
+        for s in &self.pellets {
+            let (sx, sy) = to_screen(s.x, s.y);
+            self.draw_minimap_pellet(frame, w, h, s.kind, (sx, sy), k);
+        }
+        for g in &self.ghosts {
+            let (gx, gy) = to_screen(g.x, g.y);
+            let r = (scale / 3).max(k);
+            let color = minimap_ghost_color(g, &self.render_opts.theme);
+            rect_fill(frame, w, h, gx - r, gy - r, 2 * r + 1, 2 * r + 1, color);
+        }
+
+        // Inicio del jugador con la dirección en la que arranca
+        let (px, py) = to_screen(self.player.x, self.player.y);
+        let r = (scale / 3).max(2 * k);
+        rect_fill(frame, w, h, px - r, py - r, 2 * r, 2 * r, rgba(self.render_opts.theme.player_marker, 255));
+        let len = scale as f32;
+        let (ex, ey) = (px + (self.player.dir_x * len) as i32, py + (self.player.dir_y * len) as i32);
+        line(frame, w, h, px, py, ex, ey, [255, 255, 255, 255]);
+
+        let counts = format!("Monedas: {}   Fantasmas: {}", self.pellets.len(), self.ghosts.len());
+        let y = h - bottom + 8 * k;
+        draw_text_scaled(frame, w, h, 16, y, &counts, [220, 220, 220, 255], k);
+        draw_text_scaled(frame, w, h, 16, y + 14 * k, "Presiona Enter para empezar", [180, 220, 255, 255], k);
+    }
+
+    // Habitaciones que todavía tienen monedas (resaltado de habitaciones)
+    fn pending_rooms(&self) -> Vec<bool> {
+        let mut pending = vec![false; self.rooms.count];
+        if self.settings.room_highlight {
+            for p in &self.pellets {
+                if let Some(id) = self.room_at(p.x, p.y) {
+                    pending[id] = true;
+                }
+            }
+        }
+        pending
+    }
+
     fn render_minimap(&self, frame: &mut [u8], w: i32, h: i32) {
-        let scale = 4;
-        let pad = 6;
-        let map_w = self.level.w as i32 * scale;
-        let map_h = self.level.h as i32 * scale;
+        let k = ui_scale(h);
+        let scale = 4 * k;
+        let pad = 6 * k;
+        let map_w = self.level.w * scale;
+        let map_h = self.level.h * scale;
+        // Píxeles por unidad de mundo (scale es por celda)
+        let unit = scale as f32 / self.level.tile_scale;
 
         let origin_x = w - map_w - pad;
         let origin_y = pad;
@@ -626,21 +2810,30 @@ impl Game {
             frame,
             w,
             h,
-            origin_x - 2,
-            origin_y - 2,
-            map_w + 4,
-            map_h + 4,
+            origin_x - 2 * k,
+            origin_y - 2 * k,
+            map_w + 4 * k,
+            map_h + 4 * k,
             [0, 0, 0, 180],
         );
 
+        let pending = self.pending_rooms();
+
+        // Con mapas angostos el panel se corre a la izquierda para entrar en pantalla
+        let panel_x = origin_x.min(w - MINIMAP_PANEL_W * k - pad);
+        let panel_bottom = self.render_minimap_panel(frame, w, h, panel_x, origin_y + map_h + pad);
+        if self.show_legend {
+            self.render_ghost_legend(frame, w, h, panel_x, panel_bottom + pad);
+        }
+
+        if self.settings.minimap_rotate {
+            self.render_minimap_rotated(frame, w, h, origin_x, origin_y, map_w, map_h, scale, &pending);
+            return;
+        }
+
         for y in 0..self.level.h {
             for x in 0..self.level.w {
-                let tile = self.level.tile(x, y);
-                let color = if tile == 0 {
-                    [30, 30, 30, 255]
-                } else {
-                    wall_color(tile)
-                };
+                let color = self.minimap_cell_color(x, y, &pending);
                 rect_fill(
                     frame,
                     w,
@@ -654,21 +2847,49 @@ impl Game {
             }
         }
 
+        // Monedas
+        for s in &self.pellets {
+            let sx = (origin_x as f32 + s.x * unit) as i32;
+            let sy = (origin_y as f32 + s.y * unit) as i32;
+            self.draw_minimap_pellet(frame, w, h, s.kind, (sx, sy), k);
+        }
+
         // Fantasmas en el minimapa
-        for s in &self.sprites {
-            if s.kind == SpriteKind::Ghost {
-                let gx = origin_x as f32 + s.x * scale as f32;
-                let gy = origin_y as f32 + s.y * scale as f32;
-                rect_fill(frame, w, h, gx as i32 - 1, gy as i32 - 1, 3, 3, [255, 80, 80, 255]);
+        for (i, g) in self.ghosts.iter().enumerate() {
+            if self.debug && self.show_ghost_targets {
+                let gx = (origin_x as f32 + g.x * unit) as i32;
+                let gy = (origin_y as f32 + g.y * unit) as i32;
+                // Objetivos fuera del mapa (huida) se pegan al borde del minimapa
+                let tx = ((origin_x as f32 + g.target_x * unit) as i32).clamp(origin_x, origin_x + map_w - 1);
+                let ty = ((origin_y as f32 + g.target_y * unit) as i32).clamp(origin_y, origin_y + map_h - 1);
+                draw_target_marker(frame, w, h, (gx, gy), Some((tx, ty)));
             }
+            let Some(((mx, my), color)) = self.minimap_ghost_marker(i, g) else {
+                continue;
+            };
+            let gx = (origin_x as f32 + mx * unit) as i32;
+            let gy = (origin_y as f32 + my * unit) as i32;
+            if self.is_pinged(g) {
+                rect_fill(frame, w, h, gx - 2 * k, gy - 2 * k, 5 * k, 5 * k, [255, 255, 255, 255]);
+            }
+            rect_fill(frame, w, h, gx - k, gy - k, 3 * k, 3 * k, color);
+        }
+
+        let clip = (origin_x, origin_y, origin_x + map_w, origin_y + map_h);
+        if self.debug && self.show_rays {
+            let s = unit;
+            let to_screen = |x: f32, y: f32| (origin_x as f32 + x * s, origin_y as f32 + y * s);
+            self.draw_debug_rays(frame, w, h, &to_screen, clip);
         }
 
         // Jugador
-        let px = origin_x as f32 + self.player.x * scale as f32;
-        let py = origin_y as f32 + self.player.y * scale as f32;
-        rect_fill(frame, w, h, px as i32 - 2, py as i32 - 2, 4, 4, [255, 255, 0, 255]);
-        let dx = self.player.dir_x * 6.0;
-        let dy = self.player.dir_y * 6.0;
+        let px = origin_x as f32 + self.player.x * unit;
+        let py = origin_y as f32 + self.player.y * unit;
+        self.draw_ping_ring(frame, w, h, (px, py), unit, clip);
+        let marker = rgba(self.render_opts.theme.player_marker, 255);
+        rect_fill(frame, w, h, px as i32 - 2 * k, py as i32 - 2 * k, 4 * k, 4 * k, marker);
+        let dx = self.player.dir_x * 6.0 * k as f32;
+        let dy = self.player.dir_y * 6.0 * k as f32;
         line(
             frame,
             w,
@@ -680,28 +2901,393 @@ impl Game {
             [255, 255, 255, 255],
         );
     }
+
+    // Moneda en el minimapa: un punto de k píxeles, más grande para los power-ups
+    fn draw_minimap_pellet(
+        &self,
+        frame: &mut [u8],
+        w: i32,
+        h: i32,
+        kind: SpriteKind,
+        pos: (i32, i32),
+        k: i32,
+    ) {
+        let theme = &self.render_opts.theme;
+        let (x, y) = pos;
+        let big = match kind {
+            SpriteKind::BigDot => Some(theme.big_dot),
+            SpriteKind::PowerPellet => Some(theme.power_pellet),
+            SpriteKind::FreezePellet => Some(theme.frozen),
+            _ => None,
+        };
+        if let Some(c) = big {
+            rect_fill(frame, w, h, x - k, y - k, 2 * k, 2 * k, rgba(c, 255));
+        } else {
+            rect_fill(frame, w, h, x, y, k, k, rgba(theme.pellet, 255));
+        }
+    }
+
+    // Panel bajo el minimapa: qué es cada punto y cuántas monedas y fantasmas
+    // quedan. Devuelve la fila donde termina.
+    fn render_minimap_panel(&self, frame: &mut [u8], w: i32, h: i32, x: i32, y: i32) -> i32 {
+        let theme = &self.render_opts.theme;
+        let alive = self.ghosts.iter().filter(|g| g.state != GhostState::Eyes).count();
+        let counters = [format!("Monedas: {}", self.pellets_remaining), format!("Fantasmas: {}", alive)];
+        let legend = [
+            (rgba(theme.player_marker, 255), "Jugador"),
+            (ghost_color(theme, 0, 0, 255), "Fantasma"),
+            (rgba(theme.pellet, 255), "Moneda"),
+        ];
+        let k = ui_scale(h);
+        let rows = (legend.len() + counters.len()) as i32;
+        rect_fill(frame, w, h, x - 2 * k, y - 2 * k, MINIMAP_PANEL_W * k, (rows * 10 + 3) * k, [0, 0, 0, 180]);
+
+        let mut ry = y;
+        for (color, label) in legend {
+            rect_fill(frame, w, h, x, ry + k, 5 * k, 5 * k, color);
+            draw_text_scaled(frame, w, h, x + 10 * k, ry, label, [200, 200, 200, 255], k);
+            ry += 10 * k;
+        }
+        for txt in &counters {
+            draw_text_scaled(frame, w, h, x, ry, txt, rgba(theme.hud_text, 255), k);
+            ry += 10 * k;
+        }
+        ry
+    }
+
+    // Leyenda: un renglón por color de fantasma presente en el nivel
+    fn render_ghost_legend(&self, frame: &mut [u8], w: i32, h: i32, x: i32, y: i32) {
+        let k = ui_scale(h);
+        let mut row = 0;
+        for (i, name) in GHOST_NAMES.iter().enumerate() {
+            if !self.ghosts.iter().any(|g| g.color_index == i) {
+                continue;
+            }
+            let ry = y + row * 10 * k;
+            rect_fill(frame, w, h, x, ry + k, 6 * k, 6 * k, ghost_color(&self.render_opts.theme, i, 0, 255));
+            draw_text_scaled(frame, w, h, x + 10 * k, ry, name, [220, 220, 220, 255], k);
+            row += 1;
+        }
+    }
+
+    // Color de una celda del minimapa; con el resaltado de habitaciones, las que
+    // aún tienen monedas se ven más claras y las ya limpias más oscuras.
+    fn minimap_cell_color(&self, x: i32, y: i32, pending: &[bool]) -> [u8; 4] {
+        let tile = self.level.tile(x, y);
+        if tile == 0 && self.level.is_hazard(x, y) {
+            return [180, 50, 10, 255];
+        }
+        if tile == 0 && self.settings.room_highlight {
+            let id = self.rooms.ids[(y * self.level.w + x) as usize];
+            if id >= 0 {
+                return if pending[id as usize] { [70, 70, 100, 255] } else { [16, 16, 16, 255] };
+            }
+        }
+        let color = minimap_tile_color(tile, &self.render_opts.theme);
+        // Rastro: el piso pisado hace poco se tiñe, cada vez menos con el tiempo
+        if tile == 0 && self.settings.breadcrumbs {
+            let visited = self.trail.get((y * self.level.w + x) as usize).copied();
+            let age = self.time - visited.unwrap_or(f32::NEG_INFINITY);
+            if age < TRAIL_FADE {
+                let a = TRAIL_STRENGTH * (1.0 - age / TRAIL_FADE);
+                let mix = |i: usize| (color[i] as f32 + (TRAIL_COLOR[i] as f32 - color[i] as f32) * a) as u8;
+                return [mix(0), mix(1), mix(2), 255];
+            }
+        }
+        color
+    }
+
+    // Minimapa "tipo GPS": centrado en el jugador y girado para que mire hacia arriba.
+    // Se recorre cada pixel de la caja y se busca el tile del mundo que le toca,
+    // así el recorte a la caja sale solo.
+    #[allow(clippy::too_many_arguments)]
+    fn render_minimap_rotated(
+        &self,
+        frame: &mut [u8],
+        w: i32,
+        h: i32,
+        origin_x: i32,
+        origin_y: i32,
+        map_w: i32,
+        map_h: i32,
+        scale: i32,
+        pending: &[bool],
+    ) {
+        let p = &self.player;
+        // Ejes de pantalla: adelante = dir (hacia arriba), derecha = plane normalizado
+        let plane_len = (p.plane_x * p.plane_x + p.plane_y * p.plane_y).sqrt().max(1e-6);
+        let (rx, ry) = (p.plane_x / plane_len, p.plane_y / plane_len);
+        let (fx, fy) = (p.dir_x, p.dir_y);
+        let cx = origin_x + map_w / 2;
+        let cy = origin_y + map_h / 2;
+        // Píxeles por unidad de mundo (scale es por celda)
+        let s = scale as f32 / self.level.tile_scale;
+        let k = ui_scale(h);
+
+        for sy in origin_y..origin_y + map_h {
+            for sx in origin_x..origin_x + map_w {
+                if sx < 0 || sy < 0 || sx >= w || sy >= h {
+                    continue;
+                }
+                let right = (sx - cx) as f32 / s;
+                let fwd = -(sy - cy) as f32 / s;
+                let wx = p.x + right * rx + fwd * fx;
+                let wy = p.y + right * ry + fwd * fy;
+                let (mx, my) = self.level.cell_at(wx, wy);
+                if mx < 0 || my < 0 || mx >= self.level.w || my >= self.level.h {
+                    continue;
+                }
+                let color = self.minimap_cell_color(mx, my, pending);
+                let idx = ((sy * w + sx) * 4) as usize;
+                frame[idx..idx + 4].copy_from_slice(&color);
+            }
+        }
+
+        // Mundo -> caja del minimapa (None si queda fuera)
+        let to_box = |x: f32, y: f32| -> Option<(i32, i32)> {
+            let (ox, oy) = (x - p.x, y - p.y);
+            let bx = cx + ((ox * rx + oy * ry) * s) as i32;
+            let by = cy - ((ox * fx + oy * fy) * s) as i32;
+            if bx < origin_x || by < origin_y || bx >= origin_x + map_w || by >= origin_y + map_h {
+                None
+            } else {
+                Some((bx, by))
+            }
+        };
+
+        for s in &self.pellets {
+            if let Some((sx, sy)) = to_box(s.x, s.y) {
+                self.draw_minimap_pellet(frame, w, h, s.kind, (sx, sy), k);
+            }
+        }
+
+        for (i, g) in self.ghosts.iter().enumerate() {
+            if let Some(pos) = to_box(g.x, g.y).filter(|_| self.debug && self.show_ghost_targets) {
+                draw_target_marker(frame, w, h, pos, to_box(g.target_x, g.target_y));
+            }
+            let Some(((mx, my), color)) = self.minimap_ghost_marker(i, g) else {
+                continue;
+            };
+            if let Some((gx, gy)) = to_box(mx, my) {
+                if self.is_pinged(g) {
+                    rect_fill(frame, w, h, gx - 2 * k, gy - 2 * k, 5 * k, 5 * k, [255, 255, 255, 255]);
+                }
+                rect_fill(frame, w, h, gx - k, gy - k, 3 * k, 3 * k, color);
+            }
+        }
+
+        let clip = (origin_x, origin_y, origin_x + map_w, origin_y + map_h);
+        self.draw_ping_ring(frame, w, h, (cx as f32, cy as f32), s, clip);
+        if self.debug && self.show_rays {
+            let to_screen = |x: f32, y: f32| {
+                let (ox, oy) = (x - p.x, y - p.y);
+                (cx as f32 + (ox * rx + oy * ry) * s, cy as f32 - (ox * fx + oy * fy) * s)
+            };
+            self.draw_debug_rays(frame, w, h, &to_screen, clip);
+        }
+
+        // Jugador siempre al centro mirando hacia arriba
+        let marker = rgba(self.render_opts.theme.player_marker, 255);
+        rect_fill(frame, w, h, cx - 2 * k, cy - 2 * k, 4 * k, 4 * k, marker);
+        line(frame, w, h, cx, cy, cx, cy - 6 * k, [255, 255, 255, 255]);
+    }
+}
+
+// Color de un tile en el minimapa (vidrio/agua con su tinte)
+fn minimap_tile_color(tile: i32, theme: &Theme) -> [u8; 4] {
+    if tile == 0 {
+        [30, 30, 30, 255]
+    } else if let Some(t) = glass_tint(tile) {
+        [t[0], t[1], t[2], 255]
+    } else if let Some((frames, _)) = wall_frames(tile) {
+        rgba(frames[0], 255)
+    } else {
+        wall_color(tile, theme)
+    }
+}
+
+// Punto del fantasma en el minimapa según su estado
+fn minimap_ghost_color(g: &Ghost, theme: &Theme) -> [u8; 4] {
+    match g.state {
+        GhostState::Normal => ghost_color(theme, g.color_index, 0, 255),
+        GhostState::Frightened => rgba(theme.frightened, 255),
+        GhostState::Eyes => [230, 230, 230, 255],
+    }
+}
+
+// Debug: línea tenue del fantasma a su objetivo y una X sobre el objetivo
+fn draw_target_marker(frame: &mut [u8], w: i32, h: i32, ghost: (i32, i32), target: Option<(i32, i32)>) {
+    let Some((tx, ty)) = target else {
+        return;
+    };
+    line(frame, w, h, ghost.0, ghost.1, tx, ty, [90, 90, 60, 255]);
+    line(frame, w, h, tx - 2, ty - 2, tx + 2, ty + 2, [255, 255, 0, 255]);
+    line(frame, w, h, tx - 2, ty + 2, tx + 2, ty - 2, [255, 255, 0, 255]);
 }
 
-pub fn wall_color(id: i32) -> [u8; 4] {
-    match id % 6 {
-        0 => [200, 60, 60, 255],
-        1 => [60, 200, 60, 255],
-        2 => [60, 60, 200, 255],
-        3 => [200, 200, 60, 255],
-        4 => [200, 60, 200, 255],
-        _ => [60, 200, 200, 255],
+// Filas del menú de opciones que entran entre el título y los avisos de abajo
+// (dejando lugar para la marca de "más abajo"); el resto se ve desplazando la lista
+fn settings_rows(h: i32) -> usize {
+    ((h - 40 - 36 - SETTINGS_ROW_H) / SETTINGS_ROW_H).max(1) as usize
+}
+
+// Filas de una lista con `count` entradas que se muestran cuando solo entran
+// `rows`: el cursor queda hacia el medio salvo cerca de los extremos
+fn scroll_window(cursor: usize, count: usize, rows: usize) -> std::ops::Range<usize> {
+    let start = cursor.saturating_sub(rows / 2).min(count.saturating_sub(rows));
+    start..(start + rows).min(count)
+}
+
+// Corta el texto en renglones de hasta `max_chars` caracteres, entre palabras
+// cuando se puede
+fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        // Palabras más largas que un renglón se parten
+        while word.len() > max_chars {
+            if !row.is_empty() {
+                rows.push(std::mem::take(&mut row));
+            }
+            rows.push(word.drain(..max_chars).collect());
+        }
+        let needed = if row.is_empty() { word.len() } else { row.chars().count() + 1 + word.len() };
+        if needed > max_chars {
+            rows.push(std::mem::take(&mut row));
+        }
+        if !row.is_empty() {
+            row.push(' ');
+        }
+        row.extend(word);
+    }
+    if !row.is_empty() {
+        rows.push(row);
     }
+    rows
 }
 
 fn is_wall_level(level: &Level, x: f32, y: f32) -> bool {
     if x < 0.0 || y < 0.0 {
         return true;
     }
-    let xi = x as i32;
-    let yi = y as i32;
+    let (xi, yi) = level.cell_at(x, y);
     level.tile(xi, yi) > 0
 }
 
+// Parpadeo de la invulnerabilidad: frecuencia (rad/s) normal y final, y desde
+// cuántos segundos antes de terminar empieza a acelerarse
+const INVULN_BLINK_FREQ: f32 = 10.0;
+const INVULN_BLINK_FAST: f32 = 40.0;
+const INVULN_WARN_TIME: f32 = 0.5;
+
+// Fase del parpadeo con `t` segundos de invulnerabilidad restantes. En los
+// últimos INVULN_WARN_TIME la frecuencia sube en rampa hasta INVULN_BLINK_FAST;
+// la fase es la integral de esa frecuencia, así el parpadeo no pega saltos.
+fn invuln_blink_phase(t: f32) -> f32 {
+    if t >= INVULN_WARN_TIME {
+        return t * INVULN_BLINK_FREQ;
+    }
+    // Frecuencia en s (s < W): FREQ + (FAST - FREQ) * (1 - s / W), integrada de t a W
+    let (w, extra) = (INVULN_WARN_TIME, INVULN_BLINK_FAST - INVULN_BLINK_FREQ);
+    let ramp = INVULN_BLINK_FAST * (w - t) - extra / (2.0 * w) * (w * w - t * t);
+    INVULN_WARN_TIME * INVULN_BLINK_FREQ - ramp
+}
+
+// Avanza una animación de dos cuadros de `period` segundos cada uno. Descuenta el
+// período en vez de volver a 0 para no perder el sobrante, y con un dt grande
+// salta los cuadros que correspondan: la velocidad no depende de los FPS.
+fn advance_anim(time: &mut f32, frame: &mut usize, dt: f32, period: f32) {
+    *time += dt;
+    if *time >= period {
+        let steps = (*time / period) as usize;
+        *time -= steps as f32 * period;
+        *frame = (*frame + steps) % 2;
+    }
+}
+
+// ¿Hay línea de vista entre dos puntos? El vidrio y el agua no la cortan.
+// Con la dirección sin normalizar, el DDA mide en fracciones del segmento:
+// una pared opaca antes de 1.0 tapa la vista.
+fn line_of_sight(level: &Level, from: (f32, f32), to: (f32, f32)) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    cast_ray_through(level, from.0, from.1, dx, dy, 1.0).opaque.tile == MISS_TILE
+}
+
+// Visión limitada: el jugador está dentro del alcance, del cono frente al
+// fantasma (según su rumbo) y sin paredes en el medio
+fn ghost_sees_player(level: &Level, ghost: (f32, f32, f32), player: (f32, f32)) -> bool {
+    let (gx, gy, heading) = ghost;
+    let (dx, dy) = (player.0 - gx, player.1 - gy);
+    if dx * dx + dy * dy > GHOST_VISION_RANGE * GHOST_VISION_RANGE {
+        return false;
+    }
+    let (pi, tau) = (std::f32::consts::PI, std::f32::consts::TAU);
+    let diff = (dy.atan2(dx) - heading + pi).rem_euclid(tau) - pi;
+    diff.abs() <= GHOST_VISION_HALF_ANGLE && line_of_sight(level, (gx, gy), player)
+}
+
+// ¿Cabe un cuerpo de radio `radius` centrado en (x, y)? Con celdas de lado
+// tile_scale >= TILE_SCALE_MIN (1) y radius < 0.5 basta con probar las cuatro
+// esquinas de su caja.
+pub fn can_move_to(level: &Level, x: f32, y: f32, radius: f32) -> bool {
+    [(-radius, -radius), (radius, -radius), (-radius, radius), (radius, radius)]
+        .iter()
+        .all(|&(ox, oy)| !is_wall_level(level, x + ox, y + oy))
+}
+
+// Movimiento por ejes separados (desliza contra las paredes), en subpasos no
+// mayores que el radio para no atravesar esquinas a alta velocidad
+fn slide_move(level: &Level, x: f32, y: f32, dx: f32, dy: f32, radius: f32) -> (f32, f32) {
+    let steps = (dx.abs().max(dy.abs()) / radius).ceil().max(1.0) as i32;
+    let (sx, sy) = (dx / steps as f32, dy / steps as f32);
+    let (mut x, mut y) = (x, y);
+    for _ in 0..steps {
+        if can_move_to(level, x + sx, y, radius) {
+            x += sx;
+        }
+        if can_move_to(level, x, y + sy, radius) {
+            y += sy;
+        }
+    }
+    (x, y)
+}
+
+// Corre todo el frame (dx, dy) píxeles; los bordes que quedan al descubierto
+// conservan lo que tenían, imperceptible para unos pocos píxeles.
+fn shift_frame(frame: &mut [u8], w: i32, h: i32, dx: i32, dy: i32) {
+    if dx == 0 && dy == 0 {
+        return;
+    }
+    let (dx, dy) = (dx.clamp(-(w - 1), w - 1), dy.clamp(-(h - 1), h - 1));
+    let row = (w * 4) as usize;
+    let copy_row = |frame: &mut [u8], src_y: i32, dst_y: i32| {
+        let (src, dst) = (src_y as usize * row, dst_y as usize * row);
+        let (sx, dx4) = if dx >= 0 { (0, (dx * 4) as usize) } else { ((-dx * 4) as usize, 0) };
+        let len = row - (dx.unsigned_abs() * 4) as usize;
+        frame.copy_within(src + sx..src + sx + len, dst + dx4);
+    };
+    // Recorrer en el sentido que no pisa filas aún no copiadas
+    if dy > 0 {
+        for y in (dy..h).rev() {
+            copy_row(frame, y - dy, y);
+        }
+    } else {
+        for y in 0..h + dy {
+            copy_row(frame, y - dy, y);
+        }
+    }
+}
+
+// Multiplica el color de todo el cuadro por k (0..1)
+fn dim_frame(frame: &mut [u8], k: f32) {
+    for px in frame.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = (*c as f32 * k) as u8;
+        }
+    }
+}
+
 fn fill(frame: &mut [u8], w: i32, h: i32, r: u8, g: u8, b: u8) {
     for y in 0..h {
         for x in 0..w {
@@ -714,6 +3300,7 @@ fn fill(frame: &mut [u8], w: i32, h: i32, r: u8, g: u8, b: u8) {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn rect_fill(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, rw: i32, rh: i32, color: [u8; 4]) {
     for yy in y.max(0)..(y + rh).min(h) {
         for xx in x.max(0)..(x + rw).min(w) {
@@ -723,6 +3310,47 @@ fn rect_fill(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, rw: i32, rh: i32,
     }
 }
 
+// Círculo relleno de radio `r` (en píxeles) centrado en `c`
+fn fill_disc(frame: &mut [u8], w: i32, h: i32, c: (i32, i32), r: f32, color: [u8; 4]) {
+    let ri = r.ceil() as i32;
+    for dy in -ri..=ri {
+        for dx in -ri..=ri {
+            let (x, y) = (c.0 + dx, c.1 + dy);
+            if (dx * dx + dy * dy) as f32 <= r * r && x >= 0 && y >= 0 && x < w && y < h {
+                let idx = ((y * w + x) * 4) as usize;
+                frame[idx..idx + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+// Opacidad del fondo de los textos del HUD (0..255)
+const HUD_BACKING_ALPHA: u32 = 170;
+
+// Panel oscuro semitransparente, con esquinas redondeadas, del tamaño del texto
+// que se va a dibujar en (x, y) con esa escala
+fn text_backing(frame: &mut [u8], w: i32, h: i32, x: i32, y: i32, txt: &str, scale: i32) {
+    let (tw, th) = text_size(txt, scale);
+    let pad = 2 * scale;
+    let r = 2 * scale; // radio de las esquinas
+    let (x0, y0, x1, y1) = (x - pad, y - pad, x + tw + pad, y + th + pad);
+    for yy in y0.max(0)..y1.min(h) {
+        for xx in x0.max(0)..x1.min(w) {
+            // Distancia al centro del redondeo de la esquina más cercana (0 fuera de las esquinas)
+            let dx = (x0 + r - xx).max(xx - (x1 - 1 - r)).max(0);
+            let dy = (y0 + r - yy).max(yy - (y1 - 1 - r)).max(0);
+            if dx * dx + dy * dy > r * r {
+                continue;
+            }
+            let idx = ((yy * w + xx) * 4) as usize;
+            for c in &mut frame[idx..idx + 3] {
+                *c = (*c as u32 * (255 - HUD_BACKING_ALPHA) / 255) as u8;
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn line(frame: &mut [u8], w: i32, h: i32, x0: i32, y0: i32, x1: i32, y1: i32, color: [u8; 4]) {
     let mut x0 = x0;
     let mut y0 = y0;
@@ -749,4 +3377,480 @@ fn line(frame: &mut [u8], w: i32, h: i32, x0: i32, y0: i32, x1: i32, y1: i32, co
             y0 += sy;
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_aimed_diagonally_at_corner_stays_out_of_walls() {
+        let level = get_level(0);
+        // Esquina del borde y esquina interior donde se cruzan la fila 5 y la columna 8
+        for &(x, y, dx, dy) in &[(2.5, 2.5, -1.0, -1.0), (6.5, 7.5, 1.0, -1.0)] {
+            let mut g = Ghost::new(x, y);
+            for i in 0..60 {
+                // Pasos normales y alguno muy largo (fantasma rápido o dt grande)
+                let step = if i % 10 == 9 { 2.0 } else { 0.08 };
+                (g.x, g.y) = slide_move(&level, g.x, g.y, dx * step, dy * step, GHOST_RADIUS);
+                assert_eq!(level.tile(g.x as i32, g.y as i32), 0, "dentro de pared en ({}, {})", g.x, g.y);
+                assert!(can_move_to(&level, g.x, g.y, GHOST_RADIUS), "solapa pared en ({}, {})", g.x, g.y);
+            }
+        }
+    }
+
+    #[test]
+    fn many_small_rotations_keep_camera_orthonormal() {
+        let mut p = Player::new(1.5, 1.5);
+        for i in 0..10_000 {
+            // Alternar sentidos y magnitudes para acumular error
+            let angle = if i % 3 == 0 { -0.0137 } else { 0.0213 };
+            p.rotate(angle);
+
+            let dir_len = (p.dir_x * p.dir_x + p.dir_y * p.dir_y).sqrt();
+            let dot = p.dir_x * p.plane_x + p.dir_y * p.plane_y;
+            assert!((dir_len - 1.0).abs() < 1e-3, "dir no unitario: {}", dir_len);
+            assert!(dot.abs() < 1e-3, "plane no perpendicular: {}", dot);
+        }
+        let plane_len = (p.plane_x * p.plane_x + p.plane_y * p.plane_y).sqrt();
+        assert!((plane_len - PLANE_LEN).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fov_ratio_stays_constant_after_many_rotations() {
+        let ratio = |p: &Player| {
+            (p.plane_x * p.plane_x + p.plane_y * p.plane_y).sqrt() / (p.dir_x * p.dir_x + p.dir_y * p.dir_y).sqrt()
+        };
+        let mut p = Player::new(1.5, 1.5);
+        let initial = ratio(&p);
+        for i in 0..100_000 {
+            p.rotate(if i % 2 == 0 { 0.0011 } else { -0.0007 });
+        }
+        assert!((ratio(&p) - initial).abs() < 1e-5, "FOV derivó: {} vs {}", ratio(&p), initial);
+        let dot = p.dir_x * p.plane_x + p.dir_y * p.plane_y;
+        assert!(dot.abs() < 1e-5, "plane no perpendicular: {}", dot);
+    }
+
+    fn headless_game() -> Game {
+        let mut game = Game::new(320, 240).expect("juego sin ventana");
+        game.start_level(0);
+        game
+    }
+
+    #[test]
+    fn level_preview_waits_for_enter() {
+        let mut game = Game::new(320, 240).expect("juego sin ventana");
+        game.on_key(VirtualKeyCode::Key2, true);
+        assert!(game.mode == Mode::LevelPreview);
+        assert_eq!(game.level_index, 1);
+        // El tiempo no corre mientras se mira el mapa
+        game.step(FIXED_DT);
+        assert_eq!(game.time, 0.0);
+        let mut frame = vec![0u8; 320 * 240 * 4];
+        game.render(&mut frame, 320, 240);
+        game.on_key(VirtualKeyCode::Return, true);
+        assert!(game.mode == Mode::Playing);
+
+        // Con la opción apagada se empieza directo
+        game.mode = Mode::Menu;
+        game.settings.level_preview = false;
+        game.on_key(VirtualKeyCode::Key1, true);
+        assert!(game.mode == Mode::Playing);
+    }
+
+    #[test]
+    fn top_down_view_keeps_the_player_centered() {
+        let mut game = headless_game();
+        game.on_key(VirtualKeyCode::V, true);
+        assert!(game.view_mode == ViewMode::TopDown);
+        let (w, h) = (320, 240);
+        let mut frame = vec![0u8; (w * h * 4) as usize];
+        for _ in 0..2 {
+            game.render(&mut frame, w, h);
+            // Un pixel en diagonal: la línea de dirección arranca en un cardinal
+            let idx = (((h / 2 + 1) * w + w / 2 + 1) * 4) as usize;
+            assert_eq!(&frame[idx..idx + 4], &rgba(game.render_opts.theme.player_marker, 255));
+            game.on_key(VirtualKeyCode::W, true);
+            game.step(FIXED_DT * 10.0);
+        }
+    }
+
+    #[test]
+    fn rooms_are_marked_once_when_entered() {
+        let mut game = headless_game();
+        let entered = |g: &Game| g.rooms_entered.iter().filter(|&&e| e).count();
+        // Primer nivel con alguna habitación fuera de la del inicio
+        let (start, cell) = (0..LEVEL_COUNT)
+            .find_map(|idx| {
+                game.start_level(idx);
+                let start = game.room_at(game.player.x, game.player.y).map_or(-1, |id| id as i32);
+                let cell = (0..game.level.w * game.level.h).find(|&i| {
+                    let id = game.rooms.ids[i as usize];
+                    id >= 0 && id != start
+                })?;
+                Some((start, cell))
+            })
+            .expect("algún nivel con más de una habitación");
+        assert_eq!(entered(&game), (start >= 0) as usize);
+
+        // Llevar al jugador a otra habitación
+        let (x, y) = game.level.cell_center(cell % game.level.w, cell / game.level.w);
+        (game.player.x, game.player.y) = (x, y);
+        game.update_room_entry();
+        game.update_room_entry();
+        assert_eq!(entered(&game), (start >= 0) as usize + 1);
+        assert!(game.rooms_entered[game.room_at(x, y).unwrap()]);
+    }
+
+    #[test]
+    fn gentle_bumps_push_back_until_contact_repeats() {
+        let mut game = headless_game();
+        game.settings.gentle_bumps = true;
+        let lives = game.lives;
+        for bump in 1..=BUMP_LIMIT {
+            // Fantasma pegado al jugador, un poco al oeste
+            game.ghosts.truncate(1);
+            let (px, py) = (game.player.x, game.player.y);
+            (game.ghosts[0].x, game.ghosts[0].y) = (px - 0.1, py);
+            game.check_collisions_and_pickups();
+            if bump < BUMP_LIMIT {
+                assert_eq!(game.lives, lives, "choque {}", bump);
+                assert!(game.stun_time > 0.0 && game.knockback.0 > 0.0, "empuje hacia el este");
+                // Mientras está aturdido no pasa nada más, y se desliza alejándose
+                game.check_collisions_and_pickups();
+                assert_eq!(game.lives, lives);
+                game.update_stun(0.1);
+                assert!(game.player.x > px);
+                game.stun_time = 0.0;
+                (game.player.x, game.player.y) = (px, py);
+            } else {
+                assert_eq!(game.lives, lives - 1, "choques seguidos quitan una vida");
+            }
+        }
+    }
+
+    // Juego con movimiento en grilla en un mapa chico hecho a mano
+    fn grid_game(rows: &str) -> Game {
+        let mut game = headless_game();
+        game.settings.grid_movement = true;
+        game.start_custom_level(Level::from_reader(std::io::Cursor::new(rows)).expect("mapa de prueba"));
+        game.ghosts.clear();
+        game
+    }
+
+    fn grid_run(game: &mut Game, forward: bool, steps: usize) {
+        game.pressed[VirtualKeyCode::W as usize] = forward;
+        for _ in 0..steps {
+            game.grid_step(FIXED_DT);
+        }
+    }
+
+    #[test]
+    fn grid_aligns_turns_at_intersections_and_reverses() {
+        // Pasillo este-oeste con una salida al norte en la columna 3
+        let mut game = grid_game("#####\n###.#\n#...P\n#####");
+        let cell = |g: &Game| g.level.cell_at(g.player.x, g.player.y);
+
+        // Alinearse desde fuera del centro, mirando casi al oeste
+        game.player.y += 0.3;
+        game.player.face(std::f32::consts::PI + 0.2);
+        grid_run(&mut game, false, 60);
+        assert_eq!(game.grid.facing, 2);
+        assert_eq!((game.player.x, game.player.y), game.level.cell_center(4, 2));
+
+        // El giro queda guardado hasta la intersección y ahí sube al norte
+        game.queue_grid_turn(VirtualKeyCode::A);
+        grid_run(&mut game, true, 60);
+        assert_eq!(cell(&game), (3, 1));
+        assert_eq!(game.grid.facing, 3);
+
+        // Bajar y dar media vuelta antes de llegar: vuelve a la celda de la que salió
+        game.queue_grid_turn(VirtualKeyCode::S);
+        grid_run(&mut game, true, 1);
+        assert_eq!(game.grid.target, Some((3, 2)));
+        game.queue_grid_turn(VirtualKeyCode::S);
+        grid_run(&mut game, true, 30);
+        assert_eq!(game.grid.facing, 3);
+        assert_eq!(game.grid.queued_turn, None);
+        assert_eq!((game.player.x, game.player.y), game.level.cell_center(3, 1));
+    }
+
+    #[test]
+    fn grid_half_turn_while_aligning_never_targets_a_wall() {
+        // Pared al este: la media vuelta al alinearse no puede apuntar ahí
+        let mut game = grid_game("#####\n#..P#\n#####");
+        game.player.x -= 0.3;
+        game.grid_step(0.0);
+        assert_eq!(game.grid.facing, 2);
+        game.queue_grid_turn(VirtualKeyCode::S);
+        for _ in 0..60 {
+            grid_run(&mut game, true, 1);
+            assert_ne!(game.grid.target, Some((4, 1)));
+        }
+        assert_eq!(game.grid.facing, 0);
+        assert_eq!((game.player.x, game.player.y), game.level.cell_center(3, 1));
+
+        // Un giro hacia una pared espera mientras se pueda seguir de frente
+        game.queue_grid_turn(VirtualKeyCode::S);
+        grid_run(&mut game, true, 1);
+        game.queue_grid_turn(VirtualKeyCode::A);
+        for _ in 0..60 {
+            if game.grid.target == Some((1, 1)) {
+                break;
+            }
+            grid_run(&mut game, true, 1);
+        }
+        assert_eq!(game.grid.target, Some((1, 1)));
+        assert_eq!(game.grid.queued_turn, Some(1));
+        assert_eq!(game.grid.facing, 2);
+    }
+
+    #[test]
+    fn settings_list_scrolls_with_the_cursor() {
+        assert_eq!(scroll_window(0, 34, 20), 0..20);
+        assert_eq!(scroll_window(15, 34, 20), 5..25);
+        assert_eq!(scroll_window(33, 34, 20), 14..34);
+        assert_eq!(scroll_window(3, 5, 20), 0..5);
+        // En el alto normal todas las opciones quedan alcanzables y en pantalla
+        let rows = settings_rows(400);
+        for cursor in 0..Settings::ITEM_COUNT {
+            let visible = scroll_window(cursor, Settings::ITEM_COUNT, rows);
+            assert!(visible.contains(&cursor));
+            assert!(40 + (visible.len() as i32) * SETTINGS_ROW_H < 400 - 36);
+        }
+    }
+
+    #[test]
+    fn eating_a_ghost_bursts_in_its_color() {
+        let mut game = headless_game();
+        game.ghosts.truncate(1);
+        let (px, py) = (game.player.x, game.player.y);
+        let g = &mut game.ghosts[0];
+        (g.x, g.y, g.state) = (px + 0.1, py, GhostState::Frightened);
+        let color = ghost_color(&game.render_opts.theme, g.color_index, 0, 255);
+        game.particles.clear();
+        game.check_collisions_and_pickups();
+        assert!(game.ghosts[0].state == GhostState::Eyes);
+        let burst = game.particles.iter().filter(|p| p.color == color).count();
+        assert_eq!(burst, GHOST_BURST_PARTICLES);
+    }
+
+    #[test]
+    fn win_when_no_pellets_remain() {
+        let mut game = headless_game();
+        assert!(!game.check_win());
+        game.pellets_remaining = 0;
+        assert!(game.check_win());
+        game.step(FIXED_DT);
+        // Primero el festejo; al terminar, la pantalla de victoria
+        assert!(game.mode == Mode::WinSequence);
+        let steps = (WIN_SEQUENCE_TIME / FIXED_DT).ceil() as usize + 1;
+        for _ in 0..steps {
+            game.step(FIXED_DT);
+        }
+        assert!(game.mode == Mode::Win);
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_by_walls_but_not_glass() {
+        let level = get_level(0);
+        assert!(line_of_sight(&level, (2.5, 2.5), (6.5, 2.5)));
+        // Pared horizontal de la fila 5
+        assert!(!line_of_sight(&level, (4.5, 2.5), (4.5, 8.5)));
+        // Ventanal de vidrio en la misma pared
+        assert!(line_of_sight(&level, (11.5, 2.5), (11.5, 8.5)));
+    }
+
+    #[test]
+    fn ghosts_only_see_the_player_inside_their_cone() {
+        let level = get_level(0);
+        let player = (6.5, 2.5);
+        // Mirando al este, hacia el jugador
+        assert!(ghost_sees_player(&level, (2.5, 2.5, 0.0), player));
+        // De espaldas (mirando al oeste)
+        assert!(!ghost_sees_player(&level, (2.5, 2.5, std::f32::consts::PI), player));
+        // De frente pero fuera de alcance
+        assert!(!ghost_sees_player(&level, (2.5, 2.5, 0.0), (2.5 + GHOST_VISION_RANGE + 1.0, 2.5)));
+        // De frente pero con la pared de la fila 5 en el medio
+        assert!(!ghost_sees_player(&level, (4.5, 8.5, -std::f32::consts::FRAC_PI_2), (4.5, 2.5)));
+    }
+
+    #[test]
+    fn random_ghost_spawns_keep_their_distance() {
+        use rand::SeedableRng;
+        let level = get_level(2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut ghosts = Vec::new();
+        for _ in 0..level.ghost_count {
+            Game::spawn_random_ghost(&level, &mut ghosts, &mut rng);
+        }
+        assert_eq!(ghosts.len(), level.ghost_count);
+        for (i, a) in ghosts.iter().enumerate() {
+            for b in &ghosts[i + 1..] {
+                let d = ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+                assert!(d >= GHOST_SPAWN_SPACING, "fantasmas a {:.2} celdas", d);
+            }
+        }
+    }
+
+    #[test]
+    fn ghost_speeds_vary_but_keep_the_average() {
+        for idx in 0..LEVEL_COUNT {
+            let (mut pellets, mut ghosts) = (Vec::new(), Vec::new());
+            Game::build_sprites_for_level(&get_level(idx), &mut pellets, &mut ghosts, false);
+            let mean = ghosts.iter().map(|g| g.speed).sum::<f32>() / ghosts.len() as f32;
+            assert!((mean - 1.0).abs() < 1e-5, "nivel {}: promedio {}", idx, mean);
+            assert!(ghosts.iter().all(|g| (g.speed - 1.0).abs() <= GHOST_SPEED_SPREAD + 1e-6));
+            if ghosts.len() > 1 {
+                assert!(ghosts.iter().any(|g| g.speed != ghosts[0].speed), "nivel {}: todos iguales", idx);
+            }
+        }
+    }
+
+    #[test]
+    fn win_records_stats_and_only_faster_times_are_records() {
+        let mut game = headless_game();
+        game.time = 40.0;
+        game.pellets_remaining = 0;
+        game.step(FIXED_DT);
+        let first = game.last_stats.as_ref().expect("resumen al ganar");
+        assert!(first.new_best_time);
+        assert_eq!(first.collected, game.total_pellets);
+
+        // Otra vez el mismo nivel, más lento: no es récord de tiempo
+        game.start_level(0);
+        game.time = 60.0;
+        game.pellets_remaining = 0;
+        game.step(FIXED_DT);
+        assert!(!game.last_stats.as_ref().unwrap().new_best_time);
+    }
+
+    #[test]
+    fn win_at_required_fraction() {
+        let mut game = headless_game();
+        game.start_level(2);
+        let required = game.level.required_pellets(game.total_pellets);
+        assert!(required < game.total_pellets);
+        game.pellets_remaining = game.total_pellets - required + 1;
+        assert!(!game.check_win());
+        game.pellets_remaining -= 1;
+        assert!(game.check_win());
+    }
+
+    #[test]
+    fn loss_when_out_of_lives_or_time() {
+        let mut game = headless_game();
+        assert!(!game.check_loss());
+        game.lives = 0;
+        assert!(game.check_loss());
+
+        let mut game = headless_game();
+        game.time_left = Some(0.01);
+        game.step(FIXED_DT);
+        assert!(game.check_loss());
+        assert!(game.mode == Mode::GameOver && game.time_up);
+    }
+
+    #[test]
+    fn tick_drives_the_game_with_synthetic_input() {
+        let mut game = headless_game();
+        game.settings.practice = true; // que ningún fantasma lo devuelva al spawn
+        let (x0, dir0) = (game.player.x, game.player.dir_x);
+
+        // Mantener W medio segundo: el nivel 1 arranca mirando al este
+        let mut input = InputState::default();
+        input.key(VirtualKeyCode::W, true);
+        for _ in 0..30 {
+            assert!(game.tick(FIXED_DT, &input));
+            input.clear();
+        }
+        assert!(game.player.x > x0 + 0.5, "no avanzó: {} -> {}", x0, game.player.x);
+
+        // Soltar W y mover el mouse: gira sin avanzar
+        input.key(VirtualKeyCode::W, false);
+        input.mouse_motion(200.0, 0.0);
+        let x1 = game.player.x;
+        assert!(game.tick(FIXED_DT, &input));
+        input.clear();
+        assert_eq!(game.player.x, x1);
+        assert!((game.player.dir_x - dir0).abs() > 0.1);
+
+        // Escape sin ayuda abierta pide salir
+        input.key(VirtualKeyCode::Escape, true);
+        assert!(!game.tick(FIXED_DT, &input));
+    }
+
+    #[test]
+    fn mouse_acceleration_does_not_depend_on_framerate() {
+        // Los mismos eventos en un solo tick o repartidos en varios giran igual
+        let turn = |per_tick: usize| {
+            let mut game = headless_game();
+            game.settings.mouse_accel = true;
+            game.settings.mouse_smoothing = 0.0;
+            let mut input = InputState::default();
+            for chunk in [8.0; 6].chunks(per_tick) {
+                chunk.iter().for_each(|&dx| input.mouse_motion(dx, 0.0));
+                game.tick(FIXED_DT, &input);
+                input.clear();
+            }
+            game.player.dir_y.atan2(game.player.dir_x)
+        };
+        assert!((turn(1) - turn(6)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn invert_mouse_y_flips_the_pitch() {
+        let pitch_after_moving_up = |invert: bool| {
+            let mut game = headless_game();
+            game.settings.invert_mouse_y = invert;
+            let mut input = InputState::default();
+            input.mouse_motion(0.0, -40.0);
+            game.tick(FIXED_DT, &input);
+            game.pitch
+        };
+        assert!(pitch_after_moving_up(false) > 0.0);
+        assert_eq!(pitch_after_moving_up(true), -pitch_after_moving_up(false));
+    }
+
+    #[test]
+    fn home_key_resets_the_view() {
+        let mut game = headless_game();
+        let facing = game.player.dir_y.atan2(game.player.dir_x);
+        game.pitch = 0.4;
+        game.player.rotate(1.0);
+        game.player.plane_x *= 1.5;
+        game.on_key(VirtualKeyCode::Home, true);
+        assert_eq!(game.pitch, 0.0);
+        assert!((game.player.dir_y.atan2(game.player.dir_x) - facing).abs() < 1e-4);
+        let plane_len = game.player.plane_x.hypot(game.player.plane_y);
+        assert!((plane_len - PLANE_LEN).abs() < 1e-4);
+        assert!(game.view_reset_flash > 0.0);
+    }
+
+    #[test]
+    fn ghosts_slide_on_ice() {
+        let first_step = |ice: bool| {
+            let mut game = headless_game();
+            let text = "#########\n#.......#\n#.......#\n#...P...#\n#.......#\n#.......#\n#########\n";
+            game.level = Level::from_reader(std::io::Cursor::new(text)).expect("nivel válido");
+            if ice {
+                game.level.ice = vec![true; (game.level.w * game.level.h) as usize];
+            }
+            game.ghosts = vec![Ghost::new(2.5, 2.5)];
+            game.update_sprites(FIXED_DT);
+            let g = &game.ghosts[0];
+            g.vel_x.hypot(g.vel_y)
+        };
+        let (dry, icy) = (first_step(false), first_step(true));
+        assert!(dry > 0.0);
+        assert!((icy - dry * ICE_GRIP * FIXED_DT).abs() < 1e-3, "hielo {} seco {}", icy, dry);
+    }
+
+    #[test]
+    fn win_takes_priority_over_loss() {
+        let mut game = headless_game();
+        game.pellets_remaining = 0;
+        game.lives = 0;
+        game.step(FIXED_DT);
+        assert!(game.mode == Mode::WinSequence);
+    }
+}
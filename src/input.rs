@@ -0,0 +1,35 @@
+// Entrada acumulada entre dos cuadros, independiente del bucle de eventos.
+// main.rs la llena con los eventos de winit y un test o benchmark puede
+// armarla a mano para manejar el juego con Game::tick.
+
+use winit::event::VirtualKeyCode;
+
+#[derive(Default, Clone)]
+pub struct InputState {
+    pub keys: Vec<(VirtualKeyCode, bool)>, // cambios de tecla en orden (tecla, presionada)
+    pub mouse_deltas: Vec<(f32, f32)>,     // movimientos del mouse (dx, dy), uno por evento
+    pub clicks: u32,                       // clics izquierdos
+}
+
+impl InputState {
+    pub fn key(&mut self, key: VirtualKeyCode, pressed: bool) {
+        self.keys.push((key, pressed));
+    }
+
+    // Se guardan por separado: la aceleración depende del tamaño de cada
+    // movimiento, y sumarlos la haría depender de los cuadros por segundo
+    pub fn mouse_motion(&mut self, dx: f32, dy: f32) {
+        self.mouse_deltas.push((dx, dy));
+    }
+
+    pub fn click(&mut self) {
+        self.clicks += 1;
+    }
+
+    // Vaciar tras cada tick; el estado de teclas mantenidas lo guarda Game
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.mouse_deltas.clear();
+        self.clicks = 0;
+    }
+}
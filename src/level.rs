@@ -1,9 +1,65 @@
+use anyhow::{bail, Context};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+// Paredes semitransparentes: bloquean el paso pero se ve a través con un tinte
+pub const TILE_GLASS: i32 = 8;
+pub const TILE_WATER: i32 = 9;
+
+// Rango de tile_scale: con celdas de lado >= 1 los cuerpos (radio < 0.5) tocan
+// como mucho 2x2 celdas, y can_move_to alcanza con mirar las cuatro esquinas
+pub const TILE_SCALE_MIN: f32 = 1.0;
+pub const TILE_SCALE_MAX: f32 = 4.0;
+
+// Tinte y opacidad (canal alfa) de los tiles transparentes; None si es opaco
+pub fn glass_tint(tile: i32) -> Option<[u8; 4]> {
+    match tile {
+        TILE_GLASS => Some([150, 210, 255, 80]),
+        TILE_WATER => Some([30, 110, 200, 140]),
+        _ => None,
+    }
+}
+
+// Pared de lava: titila entre varios colores (ver wall_frames)
+pub const TILE_LAVA: i32 = 10;
+
+// Cuadros de las paredes animadas y cuánto dura cada uno; None si la pared es fija
+pub fn wall_frames(tile: i32) -> Option<(&'static [[u8; 3]], f32)> {
+    match tile {
+        TILE_LAVA => Some((&[[230, 60, 10], [255, 120, 20], [255, 180, 40], [255, 120, 20]], 0.12)),
+        _ => None,
+    }
+}
+
+// Tiles que se pueden "usar" con clic (puertas, interruptores).
+// Todavía no hay ninguno, así que el clic no tiene efecto.
+pub fn is_interactable(_tile: i32) -> bool {
+    false
+}
+
+#[derive(Clone)]
 pub struct Level {
+    pub name: &'static str,
     pub w: i32,
     pub h: i32,
     pub map: Vec<i32>,
     pub spawn: (i32, i32),
+    // Orientación inicial en grados (0 = este, 90 = sur); None mira al oeste
+    pub spawn_dir: Option<f32>,
+    // Lado de cada celda en unidades de mundo (1 = una celda por unidad),
+    // entre TILE_SCALE_MIN y TILE_SCALE_MAX
+    pub tile_scale: f32,
+    pub ghost_spawns: Vec<(i32, i32)>, // casas fijas de los fantasmas; los que falten, al azar
     pub ghost_count: usize,
+    pub time_limit: Option<f32>, // segundos en modo contrarreloj
+    pub sky_top: [u8; 3],        // degradé del cielo: color arriba
+    pub sky_horizon: [u8; 3],    // y junto al horizonte
+    // Celdas de piso con hielo (resbalan); vacío si el nivel no tiene
+    pub ice: Vec<bool>,
+    // Celdas de piso peligrosas (lava): quitan vida si se queda encima; vacío si no hay
+    pub hazard: Vec<bool>,
+    pub required_fraction: f32,     // fracción de monedas necesaria para ganar (0..1]
+    pub pellet_points: Option<u32>, // puntos por moneda; None usa el valor por defecto
 }
 
 impl Level {
@@ -14,16 +70,235 @@ impl Level {
             self.map[(y * self.w + x) as usize]
         }
     }
+
+    // Celda que contiene el punto (x, y) del mundo
+    pub fn cell_at(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.tile_scale).floor() as i32, (y / self.tile_scale).floor() as i32)
+    }
+
+    // Centro de la celda (cx, cy) en coordenadas de mundo
+    pub fn cell_center(&self, cx: i32, cy: i32) -> (f32, f32) {
+        ((cx as f32 + 0.5) * self.tile_scale, (cy as f32 + 0.5) * self.tile_scale)
+    }
+
+    // Monedas que hay que juntar para ganar con `total` en el nivel
+    pub fn required_pellets(&self, total: usize) -> usize {
+        ((total as f32 * self.required_fraction.clamp(0.0, 1.0)).ceil() as usize).min(total)
+    }
+
+    pub fn is_ice(&self, x: i32, y: i32) -> bool {
+        if self.ice.is_empty() || x < 0 || y < 0 || x >= self.w || y >= self.h {
+            return false;
+        }
+        self.ice[(y * self.w + x) as usize]
+    }
+
+    pub fn is_hazard(&self, x: i32, y: i32) -> bool {
+        if self.hazard.is_empty() || x < 0 || y < 0 || x >= self.w || y >= self.h {
+            return false;
+        }
+        self.hazard[(y * self.w + x) as usize]
+    }
 }
 
+// Formato de texto de los niveles, una fila del mapa por línea:
+//   '#' pared (tile 1), '1'..'9' pared con ese tile, '.' o ' ' piso,
+//   'P' inicio del jugador (exactamente uno), 'G' casa de un fantasma.
+// Las líneas que empiezan con ';' son comentarios, salvo '; escala = 1.5' que
+// fija el lado de las celdas (tile_scale). Las filas más cortas se completan con pared.
+impl Level {
+    pub fn from_file(path: &str) -> anyhow::Result<Level> {
+        let file = File::open(path).with_context(|| format!("No se pudo abrir {}", path))?;
+        Self::from_reader(BufReader::new(file)).with_context(|| format!("Nivel inválido en {}", path))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> anyhow::Result<Level> {
+        let mut rows = Vec::new();
+        let mut tile_scale = 1.0;
+        for line in reader.lines() {
+            let line = line.context("No se pudo leer el nivel")?;
+            let line = line.trim_end();
+            if let Some(comment) = line.strip_prefix(';') {
+                if let Some(value) = comment.trim().strip_prefix("escala") {
+                    let value = value.trim_start().strip_prefix('=').unwrap_or(value).trim();
+                    tile_scale = value.parse().with_context(|| format!("escala inválida '{}'", value))?;
+                    if !(TILE_SCALE_MIN..=TILE_SCALE_MAX).contains(&tile_scale) {
+                        bail!("la escala debe estar entre {} y {}", TILE_SCALE_MIN, TILE_SCALE_MAX);
+                    }
+                }
+            } else {
+                rows.push(line.to_string());
+            }
+        }
+        while rows.last().is_some_and(|r| r.is_empty()) {
+            rows.pop();
+        }
+        let w = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0) as i32;
+        let h = rows.len() as i32;
+        if w == 0 {
+            bail!("el mapa está vacío");
+        }
+
+        let mut map = vec![1; (w * h) as usize];
+        let mut spawn = None;
+        let mut ghost_spawns = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                let (x, y) = (x as i32, y as i32);
+                map[(y * w + x) as usize] = match ch {
+                    '#' => 1,
+                    '1'..='9' => ch as i32 - '0' as i32,
+                    '.' | ' ' => 0,
+                    'P' => {
+                        if spawn.replace((x, y)).is_some() {
+                            bail!("hay más de un inicio 'P' (fila {})", y + 1);
+                        }
+                        0
+                    }
+                    'G' => {
+                        ghost_spawns.push((x, y));
+                        0
+                    }
+                    _ => bail!("carácter desconocido '{}' en fila {}, columna {}", ch, y + 1, x + 1),
+                };
+            }
+        }
+        let spawn = spawn.context("falta el inicio del jugador 'P'")?;
+
+        Ok(Level {
+            name: "Personalizado",
+            w,
+            h,
+            map,
+            spawn,
+            spawn_dir: None,
+            tile_scale,
+            ghost_count: ghost_spawns.len(),
+            ghost_spawns,
+            time_limit: None,
+            sky_top: [20, 30, 80],
+            sky_horizon: [90, 130, 200],
+            ice: Vec::new(),
+            hazard: Vec::new(),
+            required_fraction: 1.0,
+            pellet_points: None,
+        })
+    }
+}
+
+// Segmentación del mapa en habitaciones: componentes conexas de celdas libres
+// separadas por pasillos de una celda de ancho. ids[y * w + x] = -1 para paredes,
+// pasillos y habitaciones demasiado chicas.
+pub struct Rooms {
+    pub ids: Vec<i32>,
+    pub count: usize,
+}
+
+// Menos celdas que esto no cuenta como habitación
+const MIN_ROOM_CELLS: usize = 4;
+
+impl Level {
+    // Celda libre encerrada entre paredes a ambos lados (pasillo o puerta)
+    fn is_passage(&self, x: i32, y: i32) -> bool {
+        let wall = |dx: i32, dy: i32| self.tile(x + dx, y + dy) != 0;
+        (wall(-1, 0) && wall(1, 0)) || (wall(0, -1) && wall(0, 1))
+    }
+
+    pub fn segment_rooms(&self) -> Rooms {
+        let mut ids = vec![-1; (self.w * self.h) as usize];
+        let mut count = 0;
+        let mut stack = Vec::new();
+        let mut cells = Vec::new();
+        for y in 0..self.h {
+            for x in 0..self.w {
+                let i = (y * self.w + x) as usize;
+                if ids[i] != -1 || self.tile(x, y) != 0 || self.is_passage(x, y) {
+                    continue;
+                }
+                // Flood fill (4 vecinos) sin cruzar pasillos
+                let id = count as i32;
+                ids[i] = id;
+                cells.clear();
+                stack.push((x, y));
+                while let Some((cx, cy)) = stack.pop() {
+                    cells.push((cx, cy));
+                    for (nx, ny) in [(cx + 1, cy), (cx - 1, cy), (cx, cy + 1), (cx, cy - 1)] {
+                        if nx < 0 || ny < 0 || nx >= self.w || ny >= self.h {
+                            continue;
+                        }
+                        let ni = (ny * self.w + nx) as usize;
+                        if ids[ni] == -1 && self.tile(nx, ny) == 0 && !self.is_passage(nx, ny) {
+                            ids[ni] = id;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+                if cells.len() < MIN_ROOM_CELLS {
+                    for &(cx, cy) in &cells {
+                        ids[(cy * self.w + cx) as usize] = -1;
+                    }
+                } else {
+                    count += 1;
+                }
+            }
+        }
+        Rooms { ids, count }
+    }
+}
+
+// Cantidad de niveles disponibles en get_level
+pub const LEVEL_COUNT: usize = 3;
+// Índice del tutorial: va después de la campaña y no entra en LEVEL_COUNT
+pub const TUTORIAL_LEVEL: usize = LEVEL_COUNT;
+// Índice para un nivel cargado desde afuera (--level); tampoco es de la campaña
+pub const CUSTOM_LEVEL: usize = TUTORIAL_LEVEL + 1;
+
 pub fn get_level(idx: usize) -> Level {
     match idx {
         0 => level1(),
         1 => level2(),
+        TUTORIAL_LEVEL => tutorial(),
         _ => level3(),
     }
 }
 
+// Tutorial: una sala chica sin fantasmas ni tiempo límite
+fn tutorial() -> Level {
+    let w = 9;
+    let h = 6;
+    let mut map = vec![0; (w * h) as usize];
+
+    for x in 0..w {
+        map[x as usize] = 1;
+        map[((h - 1) * w + x) as usize] = 1;
+    }
+    for y in 0..h {
+        map[(y * w) as usize] = 1;
+        map[(y * w + (w - 1)) as usize] = 1;
+    }
+    // Una columna en el medio para practicar el giro
+    map[(2 * w + 4) as usize] = 2;
+
+    Level {
+        name: "Tutorial",
+        w,
+        h,
+        map,
+        spawn: (1, 3),
+        spawn_dir: Some(0.0),
+        tile_scale: 1.0,
+        ghost_spawns: Vec::new(),
+        ghost_count: 0,
+        time_limit: None,
+        sky_top: [30, 60, 120],
+        sky_horizon: [140, 190, 230],
+        ice: Vec::new(),
+        hazard: Vec::new(),
+        required_fraction: 1.0,
+        pellet_points: None,
+    }
+}
+
 // Nivel 1: sencillo
 fn level1() -> Level {
     let w = 24;
@@ -32,11 +307,11 @@ fn level1() -> Level {
 
     // Bordes
     for x in 0..w {
-        map[(0 * w + x) as usize] = 1;
+        map[x as usize] = 1;
         map[((h - 1) * w + x) as usize] = 1;
     }
     for y in 0..h {
-        map[(y * w + 0) as usize] = 1;
+        map[(y * w) as usize] = 1;
         map[(y * w + (w - 1)) as usize] = 1;
     }
     // Algunas paredes internas
@@ -47,13 +322,29 @@ fn level1() -> Level {
         map[(y * w + 8) as usize] = 4;
         map[(y * w + 15) as usize] = 5;
     }
+    // Ventanal de vidrio en la pared horizontal
+    for x in 10..13 {
+        map[(5 * w + x) as usize] = TILE_GLASS;
+    }
 
     Level {
+        name: "El patio",
         w,
         h,
         map,
         spawn: (2, 2),
+        spawn_dir: Some(0.0),
+        tile_scale: 1.0,
+        // Uno en cada sector lejano del jugador
+        ghost_spawns: vec![(20, 2), (12, 12), (20, 12)],
         ghost_count: 3,
+        time_limit: Some(90.0),
+        sky_top: [20, 30, 80],
+        sky_horizon: [90, 130, 200],
+        ice: Vec::new(),
+        hazard: Vec::new(),
+        required_fraction: 1.0,
+        pellet_points: None,
     }
 }
 
@@ -64,11 +355,11 @@ fn level2() -> Level {
     let mut map = vec![0; (w * h) as usize];
 
     for x in 0..w {
-        map[(0 * w + x) as usize] = 2;
+        map[x as usize] = 2;
         map[((h - 1) * w + x) as usize] = 2;
     }
     for y in 0..h {
-        map[(y * w + 0) as usize] = 2;
+        map[(y * w) as usize] = 2;
         map[(y * w + (w - 1)) as usize] = 2;
     }
     for y in (2..h - 2).step_by(2) {
@@ -86,12 +377,30 @@ fn level2() -> Level {
         }
     }
 
+    // Pistas de hielo en los pasillos de arriba y abajo
+    let mut ice = vec![false; (w * h) as usize];
+    for x in 5..w - 5 {
+        ice[(w + x) as usize] = true;
+        ice[((h - 2) * w + x) as usize] = true;
+    }
+
     Level {
+        name: "Laberinto",
         w,
         h,
         map,
         spawn: (1, 1),
+        spawn_dir: None,
+        tile_scale: 1.0,
+        ghost_spawns: Vec::new(),
         ghost_count: 5,
+        time_limit: Some(150.0),
+        sky_top: [40, 15, 50],
+        sky_horizon: [200, 110, 80],
+        ice,
+        hazard: Vec::new(),
+        required_fraction: 0.9,
+        pellet_points: None,
     }
 }
 
@@ -102,11 +411,11 @@ fn level3() -> Level {
     let mut map = vec![0; (w * h) as usize];
 
     for x in 0..w {
-        map[(0 * w + x) as usize] = 3;
+        map[x as usize] = 3;
         map[((h - 1) * w + x) as usize] = 3;
     }
     for y in 0..h {
-        map[(y * w + 0) as usize] = 3;
+        map[(y * w) as usize] = 3;
         map[(y * w + (w - 1)) as usize] = 3;
     }
     for y in 2..h - 2 {
@@ -120,16 +429,94 @@ fn level3() -> Level {
     for x in 4..w - 4 {
         map[((h / 2) * w + x) as usize] = 1;
     }
+    // Tramo de agua en el pasillo central
+    for x in 13..17 {
+        map[((h / 2) * w + x) as usize] = TILE_WATER;
+    }
     for y in 4..h - 4 {
         map[(y * w + w / 3) as usize] = 2;
         map[(y * w + 2 * w / 3) as usize] = 2;
     }
+    // Un tramo de lava en la pared del tercio izquierdo, con el piso de al lado quemando
+    let mut hazard = vec![false; (w * h) as usize];
+    for y in 5..8 {
+        map[(y * w + w / 3) as usize] = TILE_LAVA;
+        for x in w / 3 + 1..w / 3 + 3 {
+            let i = (y * w + x) as usize;
+            hazard[i] = map[i] == 0;
+        }
+    }
 
     Level {
+        name: "Catacumbas",
         w,
         h,
         map,
         spawn: (2, 2),
+        spawn_dir: Some(90.0),
+        tile_scale: 1.0,
+        ghost_spawns: Vec::new(),
         ghost_count: 7,
+        time_limit: Some(210.0),
+        sky_top: [5, 5, 20],
+        sky_horizon: [40, 50, 90],
+        ice: Vec::new(),
+        hazard,
+        required_fraction: 0.8,
+        pellet_points: Some(15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn from_reader_parses_ascii_map() {
+        let text = "; sala de prueba\n#####\n#P.G#\n#.2 #\n####\n";
+        let level = Level::from_reader(Cursor::new(text)).expect("nivel válido");
+        assert_eq!((level.w, level.h), (5, 4));
+        assert_eq!(level.spawn, (1, 1));
+        assert_eq!(level.ghost_spawns, vec![(3, 1)]);
+        assert_eq!(level.ghost_count, 1);
+        assert_eq!(level.tile(2, 2), 2);
+        assert_eq!(level.tile(3, 2), 0);
+        // La última fila es más corta: se completa con pared
+        assert_eq!(level.tile(4, 3), 1);
+    }
+
+    #[test]
+    fn from_reader_rejects_bad_maps() {
+        assert!(Level::from_reader(Cursor::new("###\n#.#\n###\n")).is_err());
+        assert!(Level::from_reader(Cursor::new("#PP#\n")).is_err());
+        assert!(Level::from_reader(Cursor::new("#P?#\n")).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn segment_rooms_splits_at_doorways() {
+        // Dos salas de 3x3 unidas por una puerta de una celda en (4, 2)
+        let text = "#########\n#P..#...#\n#.......#\n#...#...#\n#########\n";
+        let level = Level::from_reader(Cursor::new(text)).expect("nivel válido");
+        let rooms = level.segment_rooms();
+        let id = |x: i32, y: i32| rooms.ids[(y * level.w + x) as usize];
+        assert_eq!(rooms.count, 2);
+        assert_eq!(id(4, 2), -1, "la puerta no es de ninguna sala");
+        assert_eq!(id(0, 0), -1);
+        assert!(id(1, 1) >= 0 && id(7, 3) >= 0);
+        assert_ne!(id(1, 1), id(7, 3));
+        assert_eq!(id(1, 1), id(3, 3));
+        assert_eq!(id(5, 1), id(7, 3));
+    }
+
+    #[test]
+    fn from_reader_reads_tile_scale_header() {
+        let level = Level::from_reader(Cursor::new("; escala = 1.5\n#P#\n")).expect("nivel válido");
+        assert_eq!(level.tile_scale, 1.5);
+        assert_eq!(level.cell_center(1, 0), (2.25, 0.75));
+        assert_eq!(Level::from_reader(Cursor::new("#P#\n")).unwrap().tile_scale, 1.0);
+        // Fuera de rango o ilegible: error en vez de un mapa con colisiones rotas
+        assert!(Level::from_reader(Cursor::new("; escala = 0.5\n#P#\n")).is_err());
+        assert!(Level::from_reader(Cursor::new("; escala = mucha\n#P#\n")).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+pub mod audio;
+pub mod capture;
+pub mod crt;
+pub mod fonts;
+pub mod game;
+pub mod input;
+pub mod level;
+pub mod palette;
+pub mod particles;
+pub mod raycaster;
+pub mod settings;
+pub mod sprites;
+pub mod theme;
@@ -1,55 +1,108 @@
-mod game;
-mod level;
-mod raycaster;
-mod audio;
-mod fonts;
-mod sprites;
-
-use crate::game::Game;
+use anyhow::Context;
+use proyecto1_raycasting::capture::{FrameRecorder, CAPTURE_DIR, CAPTURE_EVERY};
+use proyecto1_raycasting::game::Game;
+use proyecto1_raycasting::input::InputState;
+use proyecto1_raycasting::level::Level;
+use proyecto1_raycasting::settings::{MAX_LIVES, SETTINGS_PATH};
 use pixels::{Pixels, SurfaceTexture};
 use winit::{
     dpi::LogicalSize,
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Window, WindowBuilder},
 };
 
 const WIDTH: u32 = 640;
 const HEIGHT: u32 = 400;
 
-fn main() -> anyhow::Result<()> {
+fn main() {
+    // Mensaje legible en vez de un panic con backtrace
+    if let Err(e) = run() {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("Pacman 3D - Raycaster (Rust)")
         .with_inner_size(LogicalSize::new(WIDTH as f64, HEIGHT as f64))
-        .with_resizable(false)
+        .with_resizable(true)
         .build(&event_loop)
-        .unwrap();
+        .context("No se pudo crear la ventana")?;
 
     let window_size = window.inner_size();
     let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-    let mut pixels = Pixels::new(WIDTH, HEIGHT, surface_texture).unwrap();
+    let mut pixels = Pixels::new(WIDTH, HEIGHT, surface_texture)
+        .context("No se pudo inicializar el render (GPU/driver)")?;
 
-    let mut game = Game::new(WIDTH as i32, HEIGHT as i32)?;
+    let mut game = Game::new(WIDTH as i32, HEIGHT as i32).context("No se pudo iniciar el juego")?;
+    game.load_settings(SETTINGS_PATH);
+    let args: Vec<String> = std::env::args().collect();
+    game.debug = args.iter().any(|a| a == "--debug");
+    if let Some(n) = args.iter().position(|a| a == "--lives").and_then(|i| args.get(i + 1)) {
+        let n: i32 = n.parse().context("--lives espera un número")?;
+        game.settings.starting_lives = n.clamp(1, MAX_LIVES);
+    }
+    // --level archivo.txt, o --level - para leer el mapa de la entrada estándar.
+    // Si no se puede cargar se muestra el error en pantalla en vez de salir.
+    if let Some(path) = args.iter().position(|a| a == "--level").and_then(|i| args.get(i + 1)) {
+        let level = if path == "-" {
+            Level::from_reader(std::io::stdin().lock()).context("Nivel inválido en la entrada estándar")
+        } else {
+            Level::from_file(path)
+        };
+        match level {
+            Ok(level) => game.start_custom_level(level),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                game.show_error(format!("{:#}", e));
+            }
+        }
+    }
 
-    // Intentar capturar el cursor (rotación con mouse horizontal)
-    let _ = window.set_cursor_grab(winit::window::CursorGrabMode::Confined);
-    window.set_cursor_visible(false);
+    // Intentar capturar el cursor (rotación con mouse horizontal). F2 lo suelta y
+    // lo vuelve a tomar; al perder el foco se suelta solo y un clic lo recaptura.
+    let mut mouse_captured = set_mouse_capture(&window, true);
 
     let mut last_time = std::time::Instant::now();
+    let mut recorder: Option<FrameRecorder> = None;
+    // Eventos de este cuadro; se aplican todos juntos en game.tick
+    let mut input = InputState::default();
 
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::DeviceEvent { event, .. } => {
-                if let DeviceEvent::MouseMotion { delta: (dx, _dy) } = event {
-                    game.on_mouse_delta(dx as f32);
-                }
-            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (dx, dy) },
+                ..
+            } if mouse_captured => input.mouse_motion(dx as f32, dy as f32),
             Event::WindowEvent { event, window_id } if window_id == window.id() => {
                 match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::CloseRequested => {
+                        game.save_settings();
+                        *control_flow = ControlFlow::Exit
+                    }
+                    // El buffer interno mantiene su tamaño; pixels lo escala y agrega
+                    // bandas negras (pillarbox/letterbox) si la ventana tiene otro aspecto
+                    WindowEvent::Resized(size) => {
+                        if let Err(e) = pixels.resize_surface(size.width, size.height) {
+                            eprintln!("Error al redimensionar: {}", e);
+                            *control_flow = ControlFlow::Exit
+                        }
+                    }
+                    WindowEvent::Focused(false) => mouse_captured = set_mouse_capture(&window, false),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F2),
+                                ..
+                            },
+                        ..
+                    } => mouse_captured = set_mouse_capture(&window, !mouse_captured),
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
@@ -58,14 +111,18 @@ fn main() -> anyhow::Result<()> {
                                 ..
                             },
                         ..
-                    } => {
-                        let pressed = state == ElementState::Pressed;
-                        if pressed && keycode == VirtualKeyCode::Escape {
-                            *control_flow = ControlFlow::Exit;
-                            return;
-                        }
-                        game.on_key(keycode, pressed);
-                    }
+                    } => input.key(keycode, state == ElementState::Pressed),
+                    // Con el cursor suelto, el clic solo sirve para volver a capturarlo
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } if !mouse_captured => mouse_captured = set_mouse_capture(&window, true),
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } => input.click(),
                     _ => {}
                 }
             }
@@ -75,18 +132,58 @@ fn main() -> anyhow::Result<()> {
                 let dt = (now - last_time).as_secs_f32();
                 last_time = now;
 
-                game.update(dt);
+                let running = game.tick(dt, &input);
+                input.clear();
+                if !running {
+                    game.save_settings();
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
 
                 // Render
                 let frame = pixels.frame_mut();
                 game.render(frame, WIDTH as i32, HEIGHT as i32);
 
+                // Grabación (F9): la carpeta se crea al empezar la primera vez
+                if game.recording {
+                    if recorder.is_none() {
+                        match FrameRecorder::new(CAPTURE_DIR, WIDTH, HEIGHT) {
+                            Ok(r) => recorder = Some(r),
+                            Err(e) => {
+                                eprintln!("Aviso: grabación deshabilitada ({:#})", e);
+                                game.recording = false;
+                            }
+                        }
+                    }
+                    if let Some(r) = recorder.as_mut() {
+                        if game.capture_frame.is_multiple_of(CAPTURE_EVERY) {
+                            r.push(frame);
+                        }
+                        game.capture_frame += 1;
+                    }
+                }
+                game.render_rec_indicator(frame, WIDTH as i32, HEIGHT as i32);
+
                 if pixels.render().is_err() {
                     *control_flow = ControlFlow::Exit;
-                    return;
                 }
             }
             _ => {}
         }
     });
-}
\ No newline at end of file
+}
+
+// Captura (true) o suelta el cursor y devuelve el nuevo estado. Si el sistema no
+// permite confinarlo igual se oculta y el mouse sigue girando la cámara.
+fn set_mouse_capture(window: &Window, capture: bool) -> bool {
+    use winit::window::CursorGrabMode;
+    let _ = if capture {
+        window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    };
+    window.set_cursor_visible(!capture);
+    capture
+}
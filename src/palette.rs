@@ -0,0 +1,103 @@
+// Selección de paleta desde las opciones. ColorBlind usa un tema fijo que evita
+// depender de rojo/verde; Standard usa el tema cargado (o el por defecto).
+
+use crate::theme::{Rgb, Theme};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Palette {
+    Standard,
+    ColorBlind,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 2] = [Palette::Standard, Palette::ColorBlind];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Standard => "Normal",
+            Palette::ColorBlind => "Daltonismo",
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Palette::Standard => Palette::ColorBlind,
+            Palette::ColorBlind => Palette::Standard,
+        }
+    }
+
+    pub fn theme(self, custom: &Theme) -> Theme {
+        match self {
+            Palette::Standard => *custom,
+            Palette::ColorBlind => Theme::color_blind(),
+        }
+    }
+}
+
+// Paletas de paredes para comparar en vivo (tecla de depuración F8). Clasica
+// deja las del tema; las demás reemplazan solo los colores de las paredes, así
+// que el render 3D y el minimapa (que usan wall_color con el tema) las siguen.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum WallPalette {
+    Classic,
+    Neon,
+    Grayscale,
+    Sepia,
+}
+
+impl WallPalette {
+    pub fn label(self) -> &'static str {
+        match self {
+            WallPalette::Classic => "Clasica",
+            WallPalette::Neon => "Neon",
+            WallPalette::Grayscale => "Grises",
+            WallPalette::Sepia => "Sepia",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            WallPalette::Classic => WallPalette::Neon,
+            WallPalette::Neon => WallPalette::Grayscale,
+            WallPalette::Grayscale => WallPalette::Sepia,
+            WallPalette::Sepia => WallPalette::Classic,
+        }
+    }
+
+    fn walls(self) -> Option<[Rgb; 6]> {
+        match self {
+            WallPalette::Classic => None,
+            WallPalette::Neon => Some([
+                [255, 20, 147],
+                [57, 255, 20],
+                [0, 255, 255],
+                [255, 255, 0],
+                [191, 0, 255],
+                [255, 110, 0],
+            ]),
+            WallPalette::Grayscale => Some([
+                [220, 220, 220],
+                [180, 180, 180],
+                [140, 140, 140],
+                [200, 200, 200],
+                [120, 120, 120],
+                [160, 160, 160],
+            ]),
+            WallPalette::Sepia => Some([
+                [170, 120, 80],
+                [200, 160, 110],
+                [140, 95, 60],
+                [220, 190, 140],
+                [120, 80, 50],
+                [185, 140, 95],
+            ]),
+        }
+    }
+
+    // Reemplaza los colores de las paredes del tema (Clasica no cambia nada)
+    pub fn apply(self, theme: &mut Theme) {
+        if let Some(walls) = self.walls() {
+            theme.walls = walls;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Partículas simples en el mundo (explosión al recoger monedas, etc.).
+// Se proyectan con la misma matemática que los sprites.
+use crate::game::Player;
+use crate::raycaster::{focal_length, project_point, DepthBuffer};
+use rand::Rng;
+
+// Máximo de partículas vivas a la vez
+pub const MAX_PARTICLES: usize = 256;
+
+const GRAVITY: f32 = 3.0;
+const FLOOR_Z: f32 = -0.5; // altura del piso respecto al ojo
+
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32, // altura respecto al horizonte (0 = altura del ojo)
+    pub vx: f32,
+    pub vy: f32,
+    pub vz: f32,
+    pub life: f32,
+    pub max_life: f32,
+    pub color: [u8; 4],
+}
+
+// Ráfaga de `count` partículas saliendo de (x, y, z) en todas direcciones
+pub fn spawn_burst(particles: &mut Vec<Particle>, x: f32, y: f32, z: f32, color: [u8; 4], count: usize) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        if particles.len() >= MAX_PARTICLES {
+            // Descarta la más vieja para hacer lugar
+            particles.remove(0);
+        }
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.gen_range(0.6..1.4);
+        let life = rng.gen_range(0.3..0.6);
+        particles.push(Particle {
+            x,
+            y,
+            z,
+            vx: angle.cos() * speed,
+            vy: angle.sin() * speed,
+            vz: rng.gen_range(0.5..1.5),
+            life,
+            max_life: life,
+            color,
+        });
+    }
+}
+
+pub fn update_particles(particles: &mut Vec<Particle>, dt: f32) {
+    for p in particles.iter_mut() {
+        p.x += p.vx * dt;
+        p.y += p.vy * dt;
+        p.z += p.vz * dt;
+        p.vz -= GRAVITY * dt;
+        p.life -= dt;
+    }
+    particles.retain(|p| p.life > 0.0 && p.z > FLOOR_Z);
+}
+
+// Cuadraditos que se desvanecen con la vida restante, ocluidos por el depth buffer
+pub fn draw_particles(
+    frame: &mut [u8],
+    w: i32,
+    h: i32,
+    player: &Player,
+    particles: &[Particle],
+    depth: &DepthBuffer,
+) {
+    let focal = focal_length(player, w);
+    for p in particles {
+        let (sx, depth_z) = match project_point(player, w, p.x, p.y) {
+            Some(v) => v,
+            None => continue,
+        };
+        if sx < 0 || sx >= w || depth_z >= depth.cols[sx as usize] {
+            continue;
+        }
+        let sy = h / 2 - (p.z * focal / depth_z) as i32;
+        let size = ((focal / depth_z) * 0.02).clamp(1.0, 4.0) as i32;
+        let a = (p.life / p.max_life).clamp(0.0, 1.0);
+
+        for y in sy..sy + size {
+            for x in sx..sx + size {
+                if x < 0 || y < 0 || x >= w || y >= h {
+                    continue;
+                }
+                let idx = ((y * w + x) * 4) as usize;
+                for c in 0..3 {
+                    frame[idx + c] = (p.color[c] as f32 * a + frame[idx + c] as f32 * (1.0 - a)) as u8;
+                }
+            }
+        }
+    }
+}
@@ -1,176 +1,839 @@
-use crate::game::{wall_color, Player};
+use crate::game::Player;
+use crate::level::{glass_tint, wall_frames, Level};
+use crate::sprites::{Facing, Ghost, GhostState, Sprite, SpriteKind};
+use crate::theme::{self, ghost_color, wall_color, Theme};
 
-// Profundidad por columna
+// Profundidad por columna, más las capas translúcidas (vidrio, agua, pared pegada)
+// que hay delante: se mezclan al final para teñir también sprites y partículas
 pub struct DepthBuffer {
     pub cols: Vec<f32>,
+    layers: Vec<ColumnLayers>,
 }
 impl DepthBuffer {
     pub fn new(width: usize) -> Self {
-        Self { cols: vec![f32::INFINITY; width] }
+        Self { cols: vec![f32::INFINITY; width], layers: vec![ColumnLayers::default(); width] }
+    }
+
+    pub fn resize(&mut self, width: usize) {
+        self.cols.resize(width, f32::INFINITY);
+        self.layers.resize(width, ColumnLayers::default());
     }
 }
 
-pub fn render_scene(
-    frame: &mut [u8],
-    w: i32,
-    h: i32,
-    level: &crate::level::Level,
-    player: &Player,
-    sprites: &[crate::sprites::Sprite],
-    depth: &mut DepthBuffer,
-) {
-    // Cielo y piso planos
-    draw_ceiling_floor(frame, w, h);
+#[derive(Copy, Clone, Default)]
+struct TranslucentLayer {
+    dist: f32,
+    color: [u8; 3],
+    alpha: f32,
+}
 
-    // Raycast de paredes sólidas (sin texturas)
-    for x in 0..w {
-        let camera_x = 2.0 * x as f32 / w as f32 - 1.0;
-        let ray_dir_x = player.dir_x + player.plane_x * camera_x;
-        let ray_dir_y = player.dir_y + player.plane_y * camera_x;
+// Capas de una columna de lejos a cerca: la pared pegada y los vidrios delante
+#[derive(Copy, Clone, Default)]
+struct ColumnLayers {
+    count: usize,
+    items: [TranslucentLayer; MAX_GLASS_LAYERS + 1],
+}
+
+impl ColumnLayers {
+    fn push(&mut self, layer: TranslucentLayer) {
+        if self.count < self.items.len() {
+            self.items[self.count] = layer;
+            self.count += 1;
+        }
+    }
+}
+
+// Reduce un frame de (2w x 2h) a (w x h) promediando bloques de 2x2 (antialiasing)
+pub fn downsample_2x(src: &[u8], dst: &mut [u8], w: i32, h: i32) {
+    let sw = (w * 2) as usize;
+    for y in 0..h as usize {
+        for x in 0..w as usize {
+            let a = ((2 * y) * sw + 2 * x) * 4;
+            let b = a + sw * 4;
+            let d = (y * w as usize + x) * 4;
+            for c in 0..3 {
+                let sum = src[a + c] as u16 + src[a + 4 + c] as u16 + src[b + c] as u16 + src[b + 4 + c] as u16;
+                dst[d + c] = (sum / 4) as u8;
+            }
+            dst[d + 3] = 255;
+        }
+    }
+}
+
+// Opciones de render ajustables desde el juego
+#[derive(Copy, Clone)]
+pub struct RenderOptions {
+    pub shadow_intensity: f32, // 0 = sin sombras, 1 = sombra negra opaca
+    pub column_step: i32,      // 1 = todas las columnas, N = una de cada N duplicada
+    pub theme: Theme,
+    pub depth_view: bool, // depuración: mostrar el depth buffer en grises
+    pub incremental_rays: bool, // direcciones de rayo incrementales (equipos lentos)
+    pub freeze_time: f32, // congelamiento restante de los fantasmas (0 = no congelados)
+    pub ping_radius: f32, // radar: se marcan los fantasmas a esta distancia (0 = apagado)
+    pub ping_time: f32,   // tiempo restante del radar (para el pulso del contorno)
+    pub max_ray_distance: f32, // corte lejano: más allá no se dibujan paredes ni sprites
+    pub depth_contrast: f32,   // brillo según la distancia de las paredes (0 = color plano)
+    pub anim_time: f32,        // reloj global para las paredes animadas (segundos)
+    pub peek_distance: f32,    // paredes más cerca que esto se ven translúcidas (0 = apagado)
+    pub sprite_culling: bool,  // descartar sprites fuera de pantalla antes de ordenar (false: benchmarks)
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            shadow_intensity: 0.45,
+            column_step: 1,
+            theme: Theme::default(),
+            depth_view: false,
+            incremental_rays: false,
+            freeze_time: 0.0,
+            ping_radius: 0.0,
+            ping_time: 0.0,
+            max_ray_distance: MAX_VIEW_DISTANCE,
+            depth_contrast: 0.0,
+            anim_time: 0.0,
+            peek_distance: 0.0,
+            sprite_culling: true,
+        }
+    }
+}
+
+// Distancia de dibujo por defecto: mayor que la diagonal de cualquier nivel,
+// así que en los mapas actuales ni el corte ni la niebla se notan
+pub const MAX_VIEW_DISTANCE: f32 = 64.0;
+// Fracción de la distancia de dibujo donde empieza la niebla
+const FOG_START: f32 = 0.7;
+
+// Contraste por distancia: variación máxima de brillo (con intensidad 1) y la
+// distancia a la que la curva llega al mínimo; a mitad de camino el color no cambia
+const DEPTH_CONTRAST_AMOUNT: f32 = 0.25;
+const DEPTH_CONTRAST_RANGE: f32 = 12.0;
+
+// Factor de brillo para una pared a `dist`: curva coseno suave de 1 + A (pegada)
+// a 1 - A (a DEPTH_CONTRAST_RANGE o más)
+fn depth_contrast_factor(dist: f32, intensity: f32) -> f32 {
+    let t = (dist / DEPTH_CONTRAST_RANGE).clamp(0.0, 1.0);
+    1.0 + intensity * DEPTH_CONTRAST_AMOUNT * (t * std::f32::consts::PI).cos()
+}
+
+// Ver a través de paredes pegadas: distancia usada por la opción del juego y
+// opacidad mínima de la pared (cuando la cámara está encima de ella)
+pub const PEEK_DISTANCE: f32 = 0.5;
+const PEEK_MIN_ALPHA: f32 = 0.35;
+
+// Distancia a partir de la cual la sombra ya no se distingue del piso
+const SHADOW_MAX_DIST: f32 = 12.0;
+
+// Grosor del contorno de radar, en unidades normalizadas del sprite
+const PING_OUTLINE: f32 = 0.08;
+
+// Resultado de lanzar un rayo por la grilla (DDA)
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+    pub dist: f32, // distancia perpendicular al plano de cámara
+    pub tile: i32, // id de la pared, o MISS_TILE si el rayo salió del mapa
+    pub side: i32, // 0: cara x, 1: cara y
+    pub map_x: i32,
+    pub map_y: i32,
+}
+
+// Rayo que sale del mapa sin chocar
+pub const MISS_TILE: i32 = -1;
+pub const MISS_DIST: f32 = 1e6;
 
-        let mut map_x = player.x as i32;
-        let mut map_y = player.y as i32;
+// Máximo de capas transparentes (vidrio/agua) que se mezclan por columna
+pub const MAX_GLASS_LAYERS: usize = 3;
+
+// Estado del recorrido DDA; permite seguir avanzando después de un choque
+struct Dda {
+    scale: f32,
+    ox: f32,
+    oy: f32,
+    ray_dir_x: f32,
+    ray_dir_y: f32,
+    map_x: i32,
+    map_y: i32,
+    step_x: i32,
+    step_y: i32,
+    side_dist_x: f32,
+    side_dist_y: f32,
+    delta_dist_x: f32,
+    delta_dist_y: f32,
+}
+
+impl Dda {
+    // El recorrido se hace en coordenadas de celda; las distancias se devuelven
+    // en unidades de mundo (multiplicadas por el tamaño de celda del nivel)
+    fn new(level: &Level, ox: f32, oy: f32, ray_dir_x: f32, ray_dir_y: f32) -> Self {
+        let scale = level.tile_scale;
+        let (ox, oy) = (ox / scale, oy / scale);
+        let map_x = ox as i32;
+        let map_y = oy as i32;
 
         let delta_dist_x = if ray_dir_x == 0.0 { f32::INFINITY } else { (1.0 / ray_dir_x).abs() };
         let delta_dist_y = if ray_dir_y == 0.0 { f32::INFINITY } else { (1.0 / ray_dir_y).abs() };
 
-        let (step_x, mut side_dist_x) = if ray_dir_x < 0.0 {
-            (-1, (player.x - map_x as f32) * delta_dist_x)
+        let (step_x, side_dist_x) = if ray_dir_x < 0.0 {
+            (-1, (ox - map_x as f32) * delta_dist_x)
         } else {
-            (1, (map_x as f32 + 1.0 - player.x) * delta_dist_x)
+            (1, (map_x as f32 + 1.0 - ox) * delta_dist_x)
         };
-        let (step_y, mut side_dist_y) = if ray_dir_y < 0.0 {
-            (-1, (player.y - map_y as f32) * delta_dist_y)
+        let (step_y, side_dist_y) = if ray_dir_y < 0.0 {
+            (-1, (oy - map_y as f32) * delta_dist_y)
         } else {
-            (1, (map_y as f32 + 1.0 - player.y) * delta_dist_y)
+            (1, (map_y as f32 + 1.0 - oy) * delta_dist_y)
         };
 
+        Self {
+            scale,
+            ox,
+            oy,
+            ray_dir_x,
+            ray_dir_y,
+            map_x,
+            map_y,
+            step_x,
+            step_y,
+            side_dist_x,
+            side_dist_y,
+            delta_dist_x,
+            delta_dist_y,
+        }
+    }
+
+    // Avanza hasta el próximo tile no vacío (o hasta salir del mapa). Si el
+    // próximo borde de celda queda más allá de max_dist se corta como si saliera
+    // del mapa: el costo por rayo queda acotado aunque el nivel sea enorme.
+    fn next_hit(&mut self, level: &Level, max_dist: f32) -> RayHit {
         let mut hit = 0;
         let mut side = 0; // 0: x, 1: y
+        let max_dist = max_dist / self.scale;
         while hit == 0 {
-            if side_dist_x < side_dist_y {
-                side_dist_x += delta_dist_x;
-                map_x += step_x;
+            if self.side_dist_x.min(self.side_dist_y) > max_dist {
+                hit = MISS_TILE;
+                break;
+            }
+            if self.side_dist_x < self.side_dist_y {
+                self.side_dist_x += self.delta_dist_x;
+                self.map_x += self.step_x;
                 side = 0;
             } else {
-                side_dist_y += delta_dist_y;
-                map_y += step_y;
+                self.side_dist_y += self.delta_dist_y;
+                self.map_y += self.step_y;
                 side = 1;
             }
-            if map_x < 0 || map_y < 0 || map_x >= level.w || map_y >= level.h {
-                hit = -1;
+            if self.map_x < 0 || self.map_y < 0 || self.map_x >= level.w || self.map_y >= level.h {
+                hit = MISS_TILE;
                 break;
             }
-            let tile = level.tile(map_x, map_y);
+            let tile = level.tile(self.map_x, self.map_y);
             if tile > 0 {
                 hit = tile;
             }
         }
 
-        let perp_wall_dist = if hit == -1 {
-            1e6
-        } else if side == 0 {
-            (map_x as f32 - player.x + (1 - step_x) as f32 / 2.0) / ray_dir_x
+        let cells = if side == 0 {
+            (self.map_x as f32 - self.ox + (1 - self.step_x) as f32 / 2.0) / self.ray_dir_x
         } else {
-            (map_y as f32 - player.y + (1 - step_y) as f32 / 2.0) / ray_dir_y
-        }
-        .abs()
-        .max(1e-4);
+            (self.map_y as f32 - self.oy + (1 - self.step_y) as f32 / 2.0) / self.ray_dir_y
+        };
+        let dist = if hit == MISS_TILE { MISS_DIST } else { cells.abs().max(1e-4) * self.scale };
 
-        let line_height = (h as f32 / perp_wall_dist) as i32;
-        let mut draw_start = -line_height / 2 + h / 2;
-        if draw_start < 0 {
-            draw_start = 0;
+        RayHit { dist, tile: hit, side, map_x: self.map_x, map_y: self.map_y }
+    }
+}
+
+// DDA desde (ox, oy) en dirección (ray_dir_x, ray_dir_y) hasta la primera pared
+pub fn cast_ray(level: &Level, ox: f32, oy: f32, ray_dir_x: f32, ray_dir_y: f32) -> RayHit {
+    Dda::new(level, ox, oy, ray_dir_x, ray_dir_y).next_hit(level, f32::INFINITY)
+}
+
+// Choques de una columna: la pared opaca final y las capas transparentes
+// atravesadas antes de llegar a ella (de cerca a lejos).
+pub struct ColumnHits {
+    pub opaque: RayHit,
+    pub glass: [RayHit; MAX_GLASS_LAYERS],
+    pub glass_count: usize,
+}
+
+// Como cast_ray, pero atraviesa vidrio/agua hasta la primera pared opaca.
+// Solo se guardan las primeras MAX_GLASS_LAYERS capas; las demás no se mezclan.
+// Más allá de max_dist el rayo termina sin pared (MISS_TILE).
+pub fn cast_ray_through(
+    level: &Level,
+    ox: f32,
+    oy: f32,
+    ray_dir_x: f32,
+    ray_dir_y: f32,
+    max_dist: f32,
+) -> ColumnHits {
+    let mut dda = Dda::new(level, ox, oy, ray_dir_x, ray_dir_y);
+    let empty = RayHit { dist: MISS_DIST, tile: MISS_TILE, side: 0, map_x: 0, map_y: 0 };
+    let mut hits = ColumnHits { opaque: empty, glass: [empty; MAX_GLASS_LAYERS], glass_count: 0 };
+    loop {
+        let hit = dda.next_hit(level, max_dist);
+        if glass_tint(hit.tile).is_none() {
+            hits.opaque = hit;
+            return hits;
         }
-        let mut draw_end = line_height / 2 + h / 2;
-        if draw_end >= h {
-            draw_end = h - 1;
+        if hits.glass_count < MAX_GLASS_LAYERS {
+            hits.glass[hits.glass_count] = hit;
+            hits.glass_count += 1;
         }
+    }
+}
 
-        let mut color = if hit > 0 { wall_color(hit) } else { [0, 0, 0, 255] };
-        if side == 1 {
-            color[0] = (color[0] as f32 * 0.7) as u8;
-            color[1] = (color[1] as f32 * 0.7) as u8;
-            color[2] = (color[2] as f32 * 0.7) as u8;
+// Primera pared opaca detrás de `near` sobre el mismo rayo, para ver a través de
+// una pared pegada a la cámara. Se saltean el vidrio/agua y la propia celda de `near`.
+fn cast_ray_behind(
+    level: &Level,
+    ox: f32,
+    oy: f32,
+    ray_dir_x: f32,
+    ray_dir_y: f32,
+    near: &RayHit,
+    max_dist: f32,
+) -> RayHit {
+    let mut dda = Dda::new(level, ox, oy, ray_dir_x, ray_dir_y);
+    loop {
+        let hit = dda.next_hit(level, max_dist);
+        let past_near = hit.dist > near.dist && (hit.map_x, hit.map_y) != (near.map_x, near.map_y);
+        if hit.tile == MISS_TILE || (past_near && glass_tint(hit.tile).is_none()) {
+            return hit;
         }
+    }
+}
 
-        for y in draw_start..=draw_end {
-            let idx = ((y * w + x) * 4) as usize;
-            frame[idx..idx + 4].copy_from_slice(&color);
-        }
+// Filas que ocupa una pared a distancia `dist`.
+// Pared pegada a la cámara: ocupa la columna completa. Se resuelve aparte
+// para no depender del redondeo de alturas enormes (sin franjas de cielo/piso).
+fn wall_span(h: i32, focal: f32, dist: f32) -> (i32, i32) {
+    let line_height_f = focal / dist;
+    if line_height_f >= h as f32 {
+        (0, h - 1)
+    } else {
+        let line_height = line_height_f as i32;
+        let draw_start = (-line_height / 2 + h / 2).max(0);
+        let draw_end = (line_height / 2 + h / 2).min(h - 1);
+        (draw_start, draw_end)
+    }
+}
+
+// Píxeles por unidad de mundo a distancia 1. Sale del FOV horizontal (|plane|) y
+// del ancho, y se usa igual en vertical: así los píxeles quedan cuadrados con
+// cualquier relación de aspecto y una moneda se ve redonda también en 16:9.
+pub fn focal_length(p: &Player, w: i32) -> f32 {
+    let plane_len = (p.plane_x * p.plane_x + p.plane_y * p.plane_y).sqrt().max(1e-6);
+    w as f32 / 2.0 / plane_len
+}
+
+// Escena completa: lo opaco y los sprites, y encima las capas translúcidas
+#[allow(clippy::too_many_arguments)]
+pub fn render_scene(
+    frame: &mut [u8],
+    w: i32,
+    h: i32,
+    level: &Level,
+    player: &Player,
+    pellets: &[Sprite],
+    ghosts: &[Ghost],
+    depth: &mut DepthBuffer,
+    opts: &RenderOptions,
+) {
+    render_scene_behind_glass(frame, w, h, level, player, pellets, ghosts, depth, opts);
+    if !opts.depth_view {
+        draw_translucent_layers(frame, w, h, player, depth);
+    }
+}
+
+// Todo lo que puede quedar detrás de un vidrio. Quien agregue más cosas a la
+// escena (partículas) las dibuja después de esto y antes de draw_translucent_layers.
+#[allow(clippy::too_many_arguments)]
+pub fn render_scene_behind_glass(
+    frame: &mut [u8],
+    w: i32,
+    h: i32,
+    level: &Level,
+    player: &Player,
+    pellets: &[Sprite],
+    ghosts: &[Ghost],
+    depth: &mut DepthBuffer,
+    opts: &RenderOptions,
+) {
+    // Cielo y piso planos
+    draw_ceiling_floor(frame, w, h, level, &opts.theme);
+    draw_floor_overlays(frame, w, h, level, player, opts.anim_time);
+
+    render_walls(frame, w, h, level, player, depth, opts);
 
-        depth.cols[x as usize] = perp_wall_dist;
+    // Vista de depuración: solo el depth buffer, sin sprites
+    if opts.depth_view {
+        draw_depth_view(frame, w, h, focal_length(player, w), depth);
+        return;
     }
 
     // Render de sprites
-    render_sprites(frame, w, h, player, sprites, depth);
+    let fog = Fog::new(level, h, opts);
+    render_sprites(frame, w, h, player, pellets, ghosts, depth, opts, &fog);
 }
 
-fn draw_ceiling_floor(frame: &mut [u8], w: i32, h: i32) {
-    let half = h / 2;
-    for y in 0..half {
-        for x in 0..w {
+// Niebla hacia los colores del horizonte (cielo arriba, piso abajo) que crece
+// cerca de la distancia de dibujo, para que el corte lejano no sea brusco
+struct Fog {
+    start: f32,
+    end: f32,
+    sky: [u8; 3],
+    floor: [u8; 3],
+    half: i32,
+}
+
+impl Fog {
+    fn new(level: &Level, h: i32, opts: &RenderOptions) -> Self {
+        Self {
+            start: opts.max_ray_distance * FOG_START,
+            end: opts.max_ray_distance,
+            sky: opts.theme.sky_horizon.unwrap_or(level.sky_horizon),
+            floor: opts.theme.floor_horizon,
+            half: h / 2,
+        }
+    }
+
+    // 0 = sin niebla, 1 = color del horizonte
+    fn amount(&self, dist: f32) -> f32 {
+        ((dist - self.start) / (self.end - self.start).max(1e-3)).clamp(0.0, 1.0)
+    }
+
+    fn apply(&self, c: [u8; 4], y: i32, amount: f32) -> [u8; 4] {
+        let target = if y < self.half { self.sky } else { self.floor };
+        let rgb = lerp_rgb([c[0], c[1], c[2]], target, amount);
+        [rgb[0], rgb[1], rgb[2], c[3]]
+    }
+}
+
+// Distancia que corresponde al negro en la vista de profundidad
+const DEPTH_VIEW_MAX_DIST: f32 = 16.0;
+
+// Reemplaza la escena por el depth buffer en grises (cerca = blanco, lejos = negro)
+fn draw_depth_view(frame: &mut [u8], w: i32, h: i32, focal: f32, depth: &DepthBuffer) {
+    for x in 0..w {
+        let d = depth.cols[x as usize];
+        let g = ((1.0 - d / DEPTH_VIEW_MAX_DIST).clamp(0.0, 1.0) * 255.0) as u8;
+        let (start, end) = if d.is_finite() { wall_span(h, focal, d) } else { (0, -1) };
+        for y in 0..h {
+            let v = if y >= start && y <= end { g } else { 0 };
             let idx = ((y * w + x) * 4) as usize;
-            frame[idx] = 40;
-            frame[idx + 1] = 60;
-            frame[idx + 2] = 120;
+            frame[idx] = v;
+            frame[idx + 1] = v;
+            frame[idx + 2] = v;
             frame[idx + 3] = 255;
         }
     }
-    for y in half..h {
+}
+
+// Dirección del rayo de cada columna dibujada, de izquierda a derecha. El modo
+// incremental suma un delta constante por columna en vez de recalcular camera_x
+// (sin conversión, división ni productos por rayo). El redondeo acumulado lo
+// aparta del cálculo directo en menos de 5e-5 con 1920 columnas: menos de una
+// décima de píxel en pantalla.
+struct ColumnRays {
+    incremental: bool,
+    w: f32,
+    dir: (f32, f32),
+    plane: (f32, f32),
+    next_dir: (f32, f32),
+    delta: (f32, f32),
+}
+
+impl ColumnRays {
+    fn new(player: &Player, w: i32, step: i32, incremental: bool) -> Self {
+        let cam_step = 2.0 * step as f32 / w as f32;
+        Self {
+            incremental,
+            w: w as f32,
+            dir: (player.dir_x, player.dir_y),
+            plane: (player.plane_x, player.plane_y),
+            next_dir: (player.dir_x - player.plane_x, player.dir_y - player.plane_y),
+            delta: (player.plane_x * cam_step, player.plane_y * cam_step),
+        }
+    }
+
+    // `x` tiene que avanzar de a `step` desde 0, como en render_walls
+    fn next(&mut self, x: i32) -> (f32, f32) {
+        if self.incremental {
+            let d = self.next_dir;
+            self.next_dir = (d.0 + self.delta.0, d.1 + self.delta.1);
+            d
+        } else {
+            let camera_x = 2.0 * x as f32 / self.w - 1.0;
+            (self.dir.0 + self.plane.0 * camera_x, self.dir.1 + self.plane.1 * camera_x)
+        }
+    }
+}
+
+// Raycast de paredes sólidas (sin texturas); llena también el depth buffer.
+// Con column_step > 1 se lanza un rayo cada N columnas y se copia a las vecinas.
+pub fn render_walls(
+    frame: &mut [u8],
+    w: i32,
+    h: i32,
+    level: &Level,
+    player: &Player,
+    depth: &mut DepthBuffer,
+    opts: &RenderOptions,
+) {
+    let step = opts.column_step.max(1);
+    let focal = focal_length(player, w);
+    let fog = Fog::new(level, h, opts);
+
+    let mut rays = ColumnRays::new(player, w, step, opts.incremental_rays);
+
+    for x in (0..w).step_by(step as usize) {
+        let (ray_dir_x, ray_dir_y) = rays.next(x);
+
+        let hits = cast_ray_through(level, player.x, player.y, ray_dir_x, ray_dir_y, opts.max_ray_distance);
+        let near = hits.opaque;
+        // Pared pegada: se dibuja primero lo que hay detrás y encima la pared translúcida
+        let peek = near.tile != MISS_TILE && near.dist < opts.peek_distance;
+        let solid = if peek {
+            cast_ray_behind(level, player.x, player.y, ray_dir_x, ray_dir_y, &near, opts.max_ray_distance)
+        } else {
+            near
+        };
+        // Sin pared dentro del alcance: queda el cielo/piso ya dibujado
+        let (draw_start, draw_end) = if solid.tile == MISS_TILE {
+            (0, -1)
+        } else {
+            wall_span(h, focal, solid.dist)
+        };
+        let fog_amount = fog.amount(solid.dist);
+        let color = wall_hit_color(&solid, opts);
+
+        for cx in x..(x + step).min(w) {
+            for y in draw_start..=draw_end {
+                let idx = ((y * w + cx) * 4) as usize;
+                let c = if fog_amount > 0.0 { fog.apply(color, y, fog_amount) } else { color };
+                frame[idx..idx + 4].copy_from_slice(&c);
+            }
+            depth.cols[cx as usize] = solid.dist;
+        }
+
+        // Capas translúcidas de lejos a cerca; se mezclan después de los sprites.
+        // La pared cercana se vuelve más transparente cuanto más pegada está.
+        let mut layers = ColumnLayers::default();
+        if peek {
+            let alpha = PEEK_MIN_ALPHA + (1.0 - PEEK_MIN_ALPHA) * near.dist / opts.peek_distance;
+            let c = wall_hit_color(&near, opts);
+            layers.push(TranslucentLayer { dist: near.dist, color: [c[0], c[1], c[2]], alpha });
+        }
+        for layer in hits.glass[..hits.glass_count].iter().rev() {
+            let Some(tint) = glass_tint(layer.tile) else {
+                continue;
+            };
+            let alpha = tint[3] as f32 / 255.0 * if layer.side == 1 { 1.0 } else { 0.85 };
+            layers.push(TranslucentLayer { dist: layer.dist, color: [tint[0], tint[1], tint[2]], alpha });
+        }
+        for cx in x..(x + step).min(w) {
+            depth.layers[cx as usize] = layers;
+        }
+    }
+}
+
+// Mezcla sobre el frame las capas translúcidas que guardó render_walls, de lejos
+// a cerca, para que tiñan las paredes, sprites y partículas que hay detrás
+pub fn draw_translucent_layers(frame: &mut [u8], w: i32, h: i32, player: &Player, depth: &DepthBuffer) {
+    let focal = focal_length(player, w);
+    for x in 0..w {
+        let column = &depth.layers[x as usize];
+        for layer in &column.items[..column.count] {
+            let a = layer.alpha;
+            let (start, end) = wall_span(h, focal, layer.dist);
+            for y in start..=end {
+                let idx = ((y * w + x) * 4) as usize;
+                for c in 0..3 {
+                    frame[idx + c] = (layer.color[c] as f32 * a + frame[idx + c] as f32 * (1.0 - a)) as u8;
+                }
+            }
+        }
+    }
+}
+
+// Color de una pared opaca: tile (animado o fijo), cara y más oscura, contraste por distancia
+fn wall_hit_color(hit: &RayHit, opts: &RenderOptions) -> [u8; 4] {
+    let mut color = if hit.tile <= 0 {
+        [0, 0, 0, 255]
+    } else if let Some((frames, frame_time)) = wall_frames(hit.tile) {
+        // Cada celda desfasada según su posición para que no titilen todas a la vez
+        let i = (opts.anim_time / frame_time) as usize + (hit.map_x + hit.map_y) as usize;
+        theme::rgba(frames[i % frames.len()], 255)
+    } else {
+        wall_color(hit.tile, &opts.theme)
+    };
+    if hit.side == 1 {
+        color[0] = (color[0] as f32 * 0.7) as u8;
+        color[1] = (color[1] as f32 * 0.7) as u8;
+        color[2] = (color[2] as f32 * 0.7) as u8;
+    }
+    // Contraste por distancia: solo cambia el brillo y se suma al oscurecido de las caras y
+    if opts.depth_contrast > 0.0 && hit.tile != MISS_TILE {
+        let f = depth_contrast_factor(hit.dist, opts.depth_contrast);
+        for c in color.iter_mut().take(3) {
+            *c = (*c as f32 * f).min(255.0) as u8;
+        }
+    }
+    color
+}
+
+fn lerp_rgb(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t) as u8;
+    [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+}
+
+// Cielo en degradé (arriba -> horizonte, colores por nivel) y piso más oscuro lejos del horizonte
+// (el tema puede reemplazar el cielo de todos los niveles)
+fn draw_ceiling_floor(frame: &mut [u8], w: i32, h: i32, level: &Level, theme: &Theme) {
+    let half = h / 2;
+    let sky_top = theme.sky_top.unwrap_or(level.sky_top);
+    let sky_horizon = theme.sky_horizon.unwrap_or(level.sky_horizon);
+    for y in 0..h {
+        let c = if y < half {
+            lerp_rgb(sky_top, sky_horizon, y as f32 / half.max(1) as f32)
+        } else {
+            let t = (y - half) as f32 / (h - half).max(1) as f32;
+            lerp_rgb(theme.floor_horizon, theme.floor_bottom, t)
+        };
+        let row = ((y * w) * 4) as usize;
+        for px in frame[row..row + (w * 4) as usize].chunks_exact_mut(4) {
+            px.copy_from_slice(&[c[0], c[1], c[2], 255]);
+        }
+    }
+}
+
+// Tinte de las celdas de hielo sobre el piso
+const ICE_COLOR: [u8; 3] = [170, 220, 255];
+
+// Piso peligroso (lava): color base y cuánto late su brillo
+const HAZARD_COLOR: [u8; 3] = [210, 60, 10];
+const HAZARD_PULSE: f32 = 0.25;
+
+// Floor casting solo para las celdas con hielo o lava: por cada fila bajo el
+// horizonte se recorre la línea del piso que ve esa fila y se tiñe lo que cae
+// en esas celdas. Las paredes se dibujan después y tapan lo que quede detrás.
+fn draw_floor_overlays(frame: &mut [u8], w: i32, h: i32, level: &Level, player: &Player, anim_time: f32) {
+    if level.ice.is_empty() && level.hazard.is_empty() {
+        return;
+    }
+    let pulse = 1.0 - HAZARD_PULSE * (0.5 + 0.5 * (anim_time * 4.0).sin());
+    let half = h / 2;
+    let focal = focal_length(player, w);
+    let (ray0_x, ray0_y) = (player.dir_x - player.plane_x, player.dir_y - player.plane_y);
+    let (ray1_x, ray1_y) = (player.dir_x + player.plane_x, player.dir_y + player.plane_y);
+    for y in half + 1..h {
+        // Ojos a media altura: la fila y ve el piso a 0.5 * focal / (y - half)
+        let row_dist = 0.5 * focal / (y - half) as f32;
+        let step_x = row_dist * (ray1_x - ray0_x) / w as f32;
+        let step_y = row_dist * (ray1_y - ray0_y) / w as f32;
+        let mut fx = player.x + row_dist * ray0_x;
+        let mut fy = player.y + row_dist * ray0_y;
+        let row = (y * w * 4) as usize;
         for x in 0..w {
-            let idx = ((y * w + x) * 4) as usize;
-            frame[idx] = 40;
-            frame[idx + 1] = 40;
-            frame[idx + 2] = 40;
-            frame[idx + 3] = 255;
+            let (cx, cy) = level.cell_at(fx, fy);
+            let idx = row + (x * 4) as usize;
+            if level.is_ice(cx, cy) {
+                // Vetas diagonales para que se note el brillo
+                let streak = ((fx + fy) * 4.0).fract() < 0.15;
+                let a = if streak { 0.75 } else { 0.55 };
+                for c in 0..3 {
+                    let v = frame[idx + c] as f32;
+                    frame[idx + c] = (v + (ICE_COLOR[c] as f32 - v) * a) as u8;
+                }
+            } else if level.is_hazard(cx, cy) {
+                // Lava opaca con brillo que late
+                for c in 0..3 {
+                    frame[idx + c] = (HAZARD_COLOR[c] as f32 * pulse) as u8;
+                }
+            }
+            fx += step_x;
+            fy += step_y;
+        }
+    }
+}
+
+// Sprite listo para dibujar: monedas y fantasmas mezclados y ordenados por distancia
+struct Billboard {
+    kind: SpriteKind,
+    anim_frame: usize,
+    spin: f32, // fase de giro de la moneda [0, 1)
+    facing: Facing,
+    ghost_state: GhostState,
+    color_index: usize,
+    blink: bool, // fantasma asustado a punto de recuperarse
+    pinged: bool, // detectado por el radar: se dibuja con contorno
+    dist2: f32,
+    transform_y: f32,  // profundidad en espacio de cámara
+    sprite_screen_x: i32,
+    sprite_h: i32,
+}
+
+// Duración de cada cuadro de animación de las monedas; todas siguen el reloj
+// global (RenderOptions::anim_time), así que giran sincronizadas
+pub const PELLET_FRAME_TIME: f32 = 0.5;
+
+// Escala por tipo: pellets más pequeños, fantasmas casi tamaño completo
+fn sprite_scale(kind: SpriteKind) -> f32 {
+    match kind {
+        SpriteKind::Pellet => 0.35, // monedas más pequeñas
+        SpriteKind::BigDot => 0.5,
+        SpriteKind::PowerPellet | SpriteKind::FreezePellet => 0.55,
+        SpriteKind::Ghost => 0.9,   // fantasmas grandes
+    }
+}
+
+// Proyecta un punto del mundo a pantalla: (columna, profundidad en cámara).
+// None si está detrás de la cámara o si dir y plane son casi colineales.
+pub fn project_point(p: &Player, w: i32, x: f32, y: f32) -> Option<(i32, f32)> {
+    let det = p.plane_x * p.dir_y - p.dir_x * p.plane_y;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let sprite_x = x - p.x;
+    let sprite_y = y - p.y;
+    let transform_x = inv_det * (p.dir_y * sprite_x - p.dir_x * sprite_y);
+    let transform_y = inv_det * (-p.plane_y * sprite_x + p.plane_x * sprite_y);
+    if transform_y <= 0.01 {
+        return None;
+    }
+
+    let screen_x = (w as f32 / 2.0 * (1.0 + transform_x / transform_y)) as i32;
+    Some((screen_x, transform_y))
+}
+
+// Desplazamiento horizontal de un fantasma congelado según el tiempo que le queda
+fn frozen_shiver(freeze_time: f32) -> i32 {
+    if freeze_time <= 0.0 {
+        return 0;
+    }
+    let amp = if freeze_time < 1.0 { 2 } else { 1 };
+    if (freeze_time * 20.0) as i32 % 2 == 0 {
+        amp
+    } else {
+        -amp
+    }
+}
+
+// Mezcla el color del fantasma con el hielo (60% hielo), manteniendo el alfa
+fn frozen_tint(c: [u8; 4], ice: theme::Rgb) -> [u8; 4] {
+    let mix = |a: u8, b: u8| ((a as u16 * 2 + b as u16 * 3) / 5) as u8;
+    [mix(c[0], ice[0]), mix(c[1], ice[1]), mix(c[2], ice[2]), c[3]]
+}
+
+// Elige el cuadro según hacia dónde se mueve el fantasma visto desde la cámara
+fn ghost_facing(g: &Ghost, p: &Player) -> Facing {
+    let to_cam = (p.y - g.y).atan2(p.x - g.x);
+    let mut rel = g.heading - to_cam;
+    while rel > std::f32::consts::PI {
+        rel -= std::f32::consts::TAU;
+    }
+    while rel < -std::f32::consts::PI {
+        rel += std::f32::consts::TAU;
+    }
+    let quarter = std::f32::consts::FRAC_PI_4;
+    if rel.abs() <= quarter {
+        Facing::Front
+    } else if rel.abs() >= 3.0 * quarter {
+        Facing::Back
+    } else {
+        // Lado en pantalla: proyección del rumbo sobre el plano de cámara
+        let side = g.heading.cos() * p.plane_x + g.heading.sin() * p.plane_y;
+        if side >= 0.0 {
+            Facing::Right
+        } else {
+            Facing::Left
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_sprites(
     frame: &mut [u8],
     w: i32,
     h: i32,
     p: &Player,
-    sprites: &[crate::sprites::Sprite],
+    pellets: &[Sprite],
+    ghosts: &[Ghost],
     depth: &DepthBuffer,
+    opts: &RenderOptions,
+    fog: &Fog,
 ) {
-    // Ordenar por distancia (lejano a cercano)
-    let mut order: Vec<(usize, f32)> = sprites
-        .iter()
-        .enumerate()
-        .map(|(i, s)| {
-            let dx = s.x - p.x;
-            let dy = s.y - p.y;
-            (i, dx * dx + dy * dy)
-        })
-        .collect();
-    order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-
-    let inv_det = 1.0 / (p.plane_x * p.dir_y - p.dir_x * p.plane_y);
-
-    for (i, _dist2) in order {
-        let s = &sprites[i];
-        let sprite_x = s.x - p.x;
-        let sprite_y = s.y - p.y;
+    let focal = focal_length(p, w);
 
-        let transform_x = inv_det * (p.dir_y * sprite_x - p.dir_x * sprite_y);
-        let transform_y = inv_det * (-p.plane_y * sprite_x + p.plane_x * sprite_y);
+    // Proyección y descarte temprano: detrás de la cámara o fuera de pantalla
+    // no entran a la lista ni al ordenamiento.
+    // Los campos de animación/estado se completan en cada llamada
+    let project = |x: f32, y: f32, kind: SpriteKind| -> Option<Billboard> {
+        let (sprite_screen_x, transform_y) = project_point(p, w, x, y)?;
+        if transform_y > opts.max_ray_distance {
+            return None;
+        }
+        let sprite_x = x - p.x;
+        let sprite_y = y - p.y;
 
-        if transform_y <= 0.01 {
-            continue;
+        let sprite_h = ((focal / transform_y) * sprite_scale(kind)).abs() as i32;
+        let half_w = sprite_h / 2; // cuadrado
+        if opts.sprite_culling && (sprite_screen_x + half_w < 0 || sprite_screen_x - half_w > w - 1) {
+            return None;
         }
 
-        let sprite_screen_x = (w as f32 / 2.0 * (1.0 + transform_x / transform_y)) as i32;
+        Some(Billboard {
+            kind,
+            anim_frame: 0,
+            spin: 0.0,
+            facing: Facing::Front,
+            ghost_state: GhostState::Normal,
+            color_index: 0,
+            blink: false,
+            pinged: false,
+            dist2: sprite_x * sprite_x + sprite_y * sprite_y,
+            transform_y,
+            sprite_screen_x,
+            sprite_h,
+        })
+    };
 
-        // Escala por tipo: pellets más pequeños, fantasmas casi tamaño completo
-        let scale = match s.kind {
-            crate::sprites::SpriteKind::Pellet => 0.35, // monedas más pequeñas
-            crate::sprites::SpriteKind::Ghost => 0.9,   // fantasmas grandes
-        };
+    let mut order: Vec<Billboard> = Vec::with_capacity(pellets.len() + ghosts.len());
+    // Una vuelta completa cada dos cuadros de animación
+    let pellet_phase = opts.anim_time / PELLET_FRAME_TIME;
+    let pellet_frame = pellet_phase as usize % 2;
+    let spin = (pellet_phase / 2.0).fract();
+    order.extend(pellets.iter().filter_map(|s| {
+        project(s.x, s.y, s.kind).map(|b| Billboard { anim_frame: pellet_frame, spin, ..b })
+    }));
+    order.extend(ghosts.iter().filter_map(|g| {
+        // Parpadeo en los últimos 2 s del susto
+        let blink = g.state == GhostState::Frightened && g.state_timer < 2.0 && g.anim_frame == 1;
+        let (dx, dy) = (g.x - p.x, g.y - p.y);
+        let pinged = g.state != GhostState::Eyes && dx * dx + dy * dy <= opts.ping_radius * opts.ping_radius;
+        project(g.x, g.y, SpriteKind::Ghost).map(|b| Billboard {
+            // Congelados tiritan: 1 px de lado a lado, más fuerte al descongelarse
+            sprite_screen_x: b.sprite_screen_x + frozen_shiver(opts.freeze_time),
+            anim_frame: g.anim_frame,
+            facing: ghost_facing(g, p),
+            ghost_state: g.state,
+            color_index: g.color_index,
+            blink,
+            pinged,
+            ..b
+        })
+    }));
+
+    // Ordenar por distancia (lejano a cercano)
+    order.sort_by(|a, b| b.dist2.partial_cmp(&a.dist2).unwrap());
 
-        let sprite_h = ((h as f32 / transform_y) * scale).abs() as i32;
+    for s in &order {
+        let transform_y = s.transform_y;
+        let sprite_screen_x = s.sprite_screen_x;
+        let sprite_h = s.sprite_h;
         let draw_start_y = (-sprite_h / 2 + h / 2).max(0);
         let draw_end_y = (sprite_h / 2 + h / 2).min(h - 1);
 
@@ -178,6 +841,12 @@ fn render_sprites(
         let draw_start_x = (-sprite_w / 2 + sprite_screen_x).max(0);
         let draw_end_x = (sprite_w / 2 + sprite_screen_x).min(w - 1);
 
+        // Sombra en el piso antes del sprite (los ojos flotando no la proyectan)
+        if s.ghost_state != GhostState::Eyes {
+            draw_sprite_shadow(frame, w, h, focal, sprite_screen_x, sprite_w, transform_y, depth, opts);
+        }
+
+        let fog_amount = fog.amount(transform_y);
         for stripe in draw_start_x..=draw_end_x {
             if transform_y >= depth.cols[stripe as usize] {
                 continue;
@@ -201,11 +870,35 @@ fn render_sprites(
                 let mut rgba = [0u8, 0u8, 0u8, 0u8];
 
                 match s.kind {
-                    crate::sprites::SpriteKind::Pellet => {
-                        // Círculo pequeño
-                        let r2 = nx * nx + cy * cy;
+                    SpriteKind::Pellet
+                    | SpriteKind::BigDot
+                    | SpriteKind::PowerPellet
+                    | SpriteKind::FreezePellet => {
+                        // Moneda girando: elipse cuyo ancho sigue |cos| de la fase
+                        // (de círculo a línea vertical y vuelta). Los power-ups no giran.
+                        let coin = if s.kind == SpriteKind::BigDot {
+                            opts.theme.big_dot
+                        } else {
+                            opts.theme.pellet
+                        };
+                        let (width, back) = if matches!(s.kind, SpriteKind::Pellet | SpriteKind::BigDot) {
+                            let c = (s.spin * std::f32::consts::TAU).cos();
+                            (c.abs().max(0.08), c < 0.0)
+                        } else {
+                            (1.0, false)
+                        };
+                        let ex = nx / width;
+                        let r2 = ex * ex + cy * cy;
                         if r2 <= 1.0 {
-                            let base = [255, 230, 0, 255];
+                            let base = match (s.kind, back) {
+                                (SpriteKind::PowerPellet, _) => theme::rgba(opts.theme.power_pellet, 255),
+                                (SpriteKind::FreezePellet, _) => theme::rgba(opts.theme.frozen, 255),
+                                // Borde más claro
+                                _ if r2 > 0.65 => [255, 250, 170, 255],
+                                // Reverso un poco más oscuro
+                                (_, true) => [coin[0] / 10 * 9, coin[1] / 10 * 8, coin[2] / 10 * 8, 255],
+                                _ => theme::rgba(coin, 255),
+                            };
                             // leve sombreado por distancia
                             let shade = ((1.2 - transform_y * 0.1).clamp(0.5, 1.0) * 255.0) as u8;
                             rgba = [
@@ -217,53 +910,43 @@ fn render_sprites(
                             write = true;
                         }
                     }
-                    crate::sprites::SpriteKind::Ghost => {
-                        // Figura de fantasma procedimental:
-                        // - cúpula superior (semicírculo)
-                        // - cuerpo rectangular
-                        // - borde inferior ondulado (3 “picos”)
+                    SpriteKind::Ghost => {
+                        // Figura de fantasma procedimental (ver ghost_shape)
                         // Coordenadas: nx [-1,1], ty [0,1]
-                        let mut inside = false;
-
-                        // Cúpula superior: círculo de radio r con centro (0, r) en espacio ty
-                        let r = 0.45;
-                        if ty <= r {
-                            let dx = nx;
-                            let dy = ty - r;
-                            if dx * dx + dy * dy <= r * r {
-                                inside = true;
-                            }
-                        }
-                        // Cuerpo
-                        if ty > r && ty <= 0.9 && nx.abs() <= 0.85 {
-                            inside = true;
-                        }
-                        // Borde inferior ondulado (tres semicúpulas)
-                        if ty > 0.9 && ty <= 1.0 {
-                            let centers = [-0.5f32, 0.0, 0.5];
-                            let rr = 0.12;
-                            for cx in centers {
-                                let dx = nx - cx;
-                                let dy = ty - 0.9;
-                                if dx * dx + dy * dy <= rr * rr {
-                                    inside = true;
-                                    break;
-                                }
-                            }
+                        let inside = ghost_shape(nx, ty, 0.0);
+
+                        // Radar: contorno pulsante justo por fuera de la figura
+                        if !inside && s.pinged && ghost_shape(nx, ty, PING_OUTLINE) {
+                            let on = (opts.ping_time * 8.0) as i32 % 2 == 0;
+                            rgba = if on { [255, 255, 255, 255] } else { [120, 255, 160, 255] };
+                            write = true;
                         }
 
                         if inside {
-                            // Color base animado (parpadeo leve usando anim_frame)
-                            let base = if s.anim_frame == 0 {
-                                [255, 120, 120, 235]
+                            // Color base animado (parpadeo leve usando anim_frame);
+                            // azul si está asustado, blanco al parpadear
+                            let base = match (s.ghost_state, s.blink, s.anim_frame) {
+                                (GhostState::Frightened, true, _) => [235, 235, 255, 235],
+                                (GhostState::Frightened, false, _) => theme::rgba(opts.theme.frightened, 235),
+                                _ => ghost_color(&opts.theme, s.color_index, s.anim_frame, 235),
+                            };
+                            let base = if opts.freeze_time > 0.0 && s.ghost_state != GhostState::Eyes {
+                                frozen_tint(base, opts.theme.frozen)
                             } else {
-                                [255, 150, 150, 235]
+                                base
+                            };
+                            // Ojos: dos círculos blancos con pupilas azules.
+                            // De frente centrados, de lado corridos hacia donde va
+                            // y de espaldas no se ven.
+                            let (eye_shift, pupil_shift) = match s.facing {
+                                Facing::Front => (0.0, 0.0),
+                                Facing::Right => (0.22, 0.05),
+                                Facing::Left => (-0.22, -0.05),
+                                Facing::Back => (0.0, 0.0),
                             };
-                            // Ojos: dos círculos blancos con pupilas azules
-                            // Posiciones relativas
                             let eye_y = 0.35;
-                            let eye_rx = 0.17;
-                            let eye_lx = -0.17;
+                            let eye_rx = 0.17 + eye_shift;
+                            let eye_lx = -0.17 + eye_shift;
                             let eye_r = 0.12;
                             let pupil_r = 0.06;
 
@@ -274,20 +957,24 @@ fn render_sprites(
                             let dry = ty - eye_y;
 
                             let mut col = base;
+                            let in_eye = dlx * dlx + dly * dly <= eye_r * eye_r
+                                || drx * drx + dry * dry <= eye_r * eye_r;
 
-                            if dlx * dlx + dly * dly <= eye_r * eye_r
-                                || drx * drx + dry * dry <= eye_r * eye_r
-                            {
+                            if s.facing != Facing::Back && in_eye {
                                 // blanco del ojo
                                 col = [250, 250, 250, 255];
-                                // Pupilas centradas
-                                let pl = dlx * dlx + dly * dly <= pupil_r * pupil_r;
-                                let pr = drx * drx + dry * dry <= pupil_r * pupil_r;
+                                // Pupilas (desplazadas hacia el lado de movimiento)
+                                let (plx, prx) = (dlx - pupil_shift, drx - pupil_shift);
+                                let pl = plx * plx + dly * dly <= pupil_r * pupil_r;
+                                let pr = prx * prx + dry * dry <= pupil_r * pupil_r;
                                 if pl || pr {
                                     col = [60, 100, 255, 255];
                                 }
                             }
 
+                            // Comido: solo quedan los ojos
+                            let visible = s.ghost_state != GhostState::Eyes || (in_eye && s.facing != Facing::Back);
+
                             // Sombreado por distancia
                             let shade = ((1.1 - transform_y * 0.08).clamp(0.5, 1.0) * 255.0) as u8;
                             rgba = [
@@ -296,12 +983,15 @@ fn render_sprites(
                                 (col[2] as u16 * shade as u16 / 255) as u8,
                                 col[3],
                             ];
-                            write = true;
+                            write = visible;
                         }
                     }
                 }
 
                 if write {
+                    if fog_amount > 0.0 {
+                        rgba = fog.apply(rgba, y, fog_amount);
+                    }
                     let idx = ((y * w + stripe) * 4) as usize;
                     frame[idx] = rgba[0];
                     frame[idx + 1] = rgba[1];
@@ -311,4 +1001,371 @@ fn render_sprites(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// ¿(nx, ty) cae dentro de la silueta del fantasma? `grow` la engorda para contornos.
+// - cúpula superior (semicírculo)
+// - cuerpo rectangular
+// - borde inferior ondulado (3 “picos”)
+fn ghost_shape(nx: f32, ty: f32, grow: f32) -> bool {
+    // Cúpula superior: círculo de radio r con centro (0, r) en espacio ty
+    let r = 0.45;
+    if ty <= r {
+        let dy = ty - r;
+        if nx * nx + dy * dy <= (r + grow) * (r + grow) {
+            return true;
+        }
+    }
+    // Cuerpo
+    if ty > r && ty <= 0.9 && nx.abs() <= 0.85 + grow {
+        return true;
+    }
+    // Borde inferior ondulado (tres semicúpulas)
+    if ty > 0.9 && ty <= 1.0 {
+        let rr = 0.12 + grow;
+        for cx in [-0.5f32, 0.0, 0.5] {
+            let dx = nx - cx;
+            let dy = ty - 0.9;
+            if dx * dx + dy * dy <= rr * rr {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Elipse oscura semitransparente sobre el piso, bajo el sprite.
+// Se apoya en la fila donde el piso corta a la distancia del sprite.
+#[allow(clippy::too_many_arguments)]
+fn draw_sprite_shadow(
+    frame: &mut [u8],
+    w: i32,
+    h: i32,
+    focal: f32,
+    sprite_screen_x: i32,
+    sprite_w: i32,
+    transform_y: f32,
+    depth: &DepthBuffer,
+    opts: &RenderOptions,
+) {
+    // Atenuar con la distancia; si ya no se vería, no dibujar
+    let alpha = opts.shadow_intensity * (1.0 - transform_y / SHADOW_MAX_DIST);
+    if alpha <= 0.02 {
+        return;
+    }
+
+    let floor_y = h / 2 + (focal / transform_y / 2.0) as i32;
+    let rx = (sprite_w as f32 * 0.4).max(1.0);
+    let ry = (sprite_w as f32 * 0.08).max(1.0);
+
+    let x0 = ((sprite_screen_x as f32 - rx) as i32).max(0);
+    let x1 = ((sprite_screen_x as f32 + rx) as i32).min(w - 1);
+    let y0 = ((floor_y as f32 - ry) as i32).max(h / 2);
+    let y1 = ((floor_y as f32 + ry) as i32).min(h - 1);
+
+    for x in x0..=x1 {
+        if transform_y >= depth.cols[x as usize] {
+            continue;
+        }
+        let nx = (x - sprite_screen_x) as f32 / rx;
+        for y in y0..=y1 {
+            let ny = (y - floor_y) as f32 / ry;
+            let r2 = nx * nx + ny * ny;
+            if r2 > 1.0 {
+                continue;
+            }
+            // Borde suave: más oscuro en el centro
+            let k = 1.0 - alpha * (1.0 - r2);
+            let idx = ((y * w + x) * 4) as usize;
+            frame[idx] = (frame[idx] as f32 * k) as u8;
+            frame[idx + 1] = (frame[idx + 1] as f32 * k) as u8;
+            frame[idx + 2] = (frame[idx + 2] as f32 * k) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nivel 5x5 con borde de paredes (tile 1) y el interior vacío
+    fn box_level() -> Level {
+        let (w, h) = (5, 5);
+        let mut map = vec![0; (w * h) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+                    map[(y * w + x) as usize] = 1;
+                }
+            }
+        }
+        Level {
+            name: "caja",
+            w,
+            h,
+            map,
+            spawn: (2, 2),
+            spawn_dir: None,
+            tile_scale: 1.0,
+            ghost_spawns: Vec::new(),
+            ghost_count: 0,
+            time_limit: None,
+            sky_top: [0, 0, 0],
+            sky_horizon: [0, 0, 0],
+            ice: Vec::new(),
+            hazard: Vec::new(),
+            required_fraction: 1.0,
+            pellet_points: None,
+        }
+    }
+
+    // La caja con una pared interior (tile 3) en (2, 2), una pared distinta (tile 2)
+    // en (2, 4) y una abertura al exterior en (4, 2):
+    //   1 1 1 1 1
+    //   1 . . . 1
+    //   1 . 3 . .
+    //   1 . . . 1
+    //   1 1 2 1 1
+    fn maze_level() -> Level {
+        let mut level = box_level();
+        let w = level.w;
+        level.map[(2 * w + 2) as usize] = 3;
+        level.map[(4 * w + 2) as usize] = 2;
+        level.map[(2 * w + 4) as usize] = 0;
+        level
+    }
+
+    fn assert_hit(hit: RayHit, tile: i32, side: i32, map: (i32, i32), dist: f32) {
+        assert_eq!(hit.tile, tile, "tile");
+        assert_eq!(hit.side, side, "cara");
+        assert_eq!((hit.map_x, hit.map_y), map, "celda");
+        assert!((hit.dist - dist).abs() < 1e-5, "distancia {} != {}", hit.dist, dist);
+    }
+
+    #[test]
+    fn dda_axis_aligned_rays_hit_expected_walls() {
+        let level = maze_level();
+        // Este: atraviesa (2,3) y (3,3) y choca el borde en x = 4
+        assert_hit(cast_ray(&level, 1.5, 3.5, 1.0, 0.0), 1, 0, (4, 3), 2.5);
+        // Norte: borde superior en y = 1
+        assert_hit(cast_ray(&level, 1.5, 3.5, 0.0, -1.0), 1, 1, (1, 0), 2.5);
+        // Desde (2.5, 3.5): al norte la pared interior, al sur la del borde con otro id
+        assert_hit(cast_ray(&level, 2.5, 3.5, 0.0, -1.0), 3, 1, (2, 2), 0.5);
+        assert_hit(cast_ray(&level, 2.5, 3.5, 0.0, 1.0), 2, 1, (2, 4), 0.5);
+    }
+
+    #[test]
+    fn dda_oblique_rays_report_perpendicular_distance() {
+        let level = maze_level();
+        // 45° (sin normalizar): cruza y = 3 en x = 1.75 y entra a la pared por su cara x = 2
+        assert_hit(cast_ray(&level, 1.25, 3.5, 1.0, -1.0), 3, 0, (2, 2), 0.75);
+        // 30° hacia el sur: pasa por (2,1) y toca la cara superior de la pared interior
+        let (s, c) = 30f32.to_radians().sin_cos();
+        assert_hit(cast_ray(&level, 1.5, 1.5, c, s), 3, 1, (2, 2), 1.0);
+    }
+
+    #[test]
+    fn dda_ray_leaving_the_map_misses() {
+        let level = maze_level();
+        // Por la abertura en (4, 2) sale del mapa sin chocar
+        let hit = cast_ray(&level, 3.5, 2.5, 1.0, 0.0);
+        assert_eq!(hit.tile, MISS_TILE);
+        assert_eq!(hit.dist, MISS_DIST);
+    }
+
+    #[test]
+    fn dda_stops_at_max_distance() {
+        let level = maze_level();
+        // La pared este está a 2.5: con alcance 1 el rayo termina antes, con 3 la alcanza
+        let near = cast_ray_through(&level, 1.5, 3.5, 1.0, 0.0, 1.0);
+        assert_eq!(near.opaque.tile, MISS_TILE);
+        let far = cast_ray_through(&level, 1.5, 3.5, 1.0, 0.0, 3.0);
+        assert_hit(far.opaque, 1, 0, (4, 3), 2.5);
+    }
+
+    #[test]
+    fn tile_scale_stretches_wall_distances() {
+        let mut level = maze_level();
+        level.tile_scale = 2.0;
+        // Mismas celdas que en el DDA sin escalar, con distancias al doble
+        assert_hit(cast_ray(&level, 3.0, 7.0, 1.0, 0.0), 1, 0, (4, 3), 5.0);
+        assert_hit(cast_ray(&level, 5.0, 7.0, 0.0, -1.0), 3, 1, (2, 2), 1.0);
+
+        // Celda (1, 3) mirando al oeste: la pared del borde queda a 1 y no a 0.5
+        let (w, h) = (64, 200);
+        let player = Player::new(3.0, 7.0);
+        let mut depth = DepthBuffer::new(w as usize);
+        let mut frame = vec![0u8; (w * h * 4) as usize];
+        render_scene(&mut frame, w, h, &level, &player, &[], &[], &mut depth, &RenderOptions::default());
+        let x = w / 2;
+        assert!((depth.cols[x as usize] - 1.0).abs() < 1e-5, "profundidad {}", depth.cols[x as usize]);
+
+        // La pared ocupa las filas que corresponden a esa distancia
+        let pixel = |y: i32| {
+            let idx = ((y * w + x) * 4) as usize;
+            frame[idx..idx + 4].to_vec()
+        };
+        let wall = pixel(h / 2);
+        let rows = (0..h).filter(|&y| pixel(y) == wall).count() as i32;
+        let (start, end) = wall_span(h, focal_length(&player, w), 1.0);
+        assert_eq!(rows, end - start + 1);
+    }
+
+    #[test]
+    fn incremental_rays_stay_close_to_direct_rays() {
+        // El error acumulado del modo incremental queda acotado aun con muchas columnas
+        for &(w, step) in &[(320, 1), (1920, 1), (1920, 3)] {
+            for &angle in &[0.0, 0.7, 2.3, -1.9] {
+                let mut player = Player::new(2.5, 2.5);
+                player.rotate(angle);
+                let mut direct = ColumnRays::new(&player, w, step, false);
+                let mut incremental = ColumnRays::new(&player, w, step, true);
+                for x in (0..w).step_by(step as usize) {
+                    let (a, b) = (direct.next(x), incremental.next(x));
+                    let err = (a.0 - b.0).abs().max((a.1 - b.1).abs());
+                    assert!(err < 5e-5, "columna {x} de {w}, giro {angle}: error {err}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cast_ray_distances_are_pinned() {
+        // Valores exactos (bits) de la división original en el DDA: cambiar la
+        // aritmética del rayo no puede mover las distancias de las paredes
+        let level = crate::level::get_level(2);
+        let (x, y) = level.cell_center(level.spawn.0, level.spawn.1);
+        let pins = [
+            (0.3f32, 0x3fb78eef, (4, 2)),
+            (1.1, 0x3f50d1f1, (3, 3)),
+            (2.0, 0x407aae46, (0, 5)),
+            (2.9, 0x3fd6e16b, (0, 2)),
+            (-0.7, 0x400027cb, (4, 0)),
+            (-2.4, 0x3ff47459, (1, 0)),
+        ];
+        for &(angle, bits, cell) in &pins {
+            let hit = cast_ray(&level, x + 0.13, y - 0.21, angle.cos(), angle.sin());
+            assert_eq!((hit.map_x, hit.map_y), cell, "giro {angle}");
+            assert_eq!(hit.dist.to_bits(), bits, "giro {angle}: {} != {}", hit.dist, f32::from_bits(bits));
+        }
+    }
+
+    #[test]
+    fn wall_adjacent_to_player_fills_whole_column() {
+        let (w, h) = (64, 40);
+        let level = box_level();
+        let mut depth = DepthBuffer::new(w as usize);
+        let opts = RenderOptions::default();
+
+        // Distancias cada vez más pequeñas hasta el mínimo de 1e-4
+        for px in [1.05_f32, 1.01, 1.001, 1.0] {
+            let mut frame = vec![0u8; (w * h * 4) as usize];
+            let player = Player::new(px, 2.5);
+            render_scene(&mut frame, w, h, &level, &player, &[], &[], &mut depth, &opts);
+
+            let x = w / 2;
+            for y in 0..h {
+                let idx = ((y * w + x) * 4) as usize;
+                assert_eq!(&frame[idx..idx + 4], &wall_color(1, &Theme::default()), "px={} fila {}", px, y);
+            }
+        }
+    }
+
+    #[test]
+    fn sprites_behind_glass_are_tinted() {
+        // Moneda detrás de un vidrio ('8'), mirando al oeste desde (5.5, 2.5)
+        let text = "#######\n#.....#\n#..8.P#\n#.....#\n#######\n";
+        let level = Level::from_reader(std::io::Cursor::new(text)).expect("nivel válido");
+        let (w, h) = (64, 40);
+        let player = Player::new(5.5, 2.5);
+        let opts = RenderOptions { shadow_intensity: 0.0, ..RenderOptions::default() };
+        let pellet = [Sprite::new(1.5, 2.5, SpriteKind::Pellet)];
+        let render = |pellets: &[Sprite], tinted: bool| {
+            let mut depth = DepthBuffer::new(w as usize);
+            let mut frame = vec![0u8; (w * h * 4) as usize];
+            if tinted {
+                render_scene(&mut frame, w, h, &level, &player, pellets, &[], &mut depth, &opts);
+            } else {
+                render_scene_behind_glass(&mut frame, w, h, &level, &player, pellets, &[], &mut depth, &opts);
+            }
+            frame
+        };
+        let (empty, behind, full) = (render(&[], false), render(&pellet, false), render(&pellet, true));
+        let tint = glass_tint(crate::level::TILE_GLASS).unwrap();
+        let a = tint[3] as f32 / 255.0 * 0.85; // se ve la cara x del vidrio
+        let mut pellet_pixels = 0;
+        for i in (0..empty.len()).step_by(4) {
+            if empty[i..i + 3] == behind[i..i + 3] {
+                continue;
+            }
+            // El vidrio se mezcla encima de la moneda, no al revés
+            pellet_pixels += 1;
+            for c in 0..3 {
+                let expected = (tint[c] as f32 * a + behind[i + c] as f32 * (1.0 - a)) as u8;
+                assert_eq!(full[i + c], expected, "pixel {} canal {}", i / 4, c);
+            }
+        }
+        assert!(pellet_pixels > 0, "la moneda no se ve");
+    }
+
+    #[test]
+    fn sprite_culling_keeps_the_frame_identical() {
+        // Sala llena de monedas: desde el centro la mayoría queda fuera del cuadro
+        let row = "#........#\n";
+        let text = format!("##########\n{}#...P....#\n{}##########\n", row.repeat(3), row.repeat(4));
+        let level = Level::from_reader(std::io::Cursor::new(text)).expect("nivel válido");
+        let mut pellets = Vec::new();
+        for y in 1..9 {
+            for x in 1..9 {
+                pellets.push(Sprite::new(x as f32 + 0.5, y as f32 + 0.5, SpriteKind::Pellet));
+            }
+        }
+        let (w, h) = (160, 100);
+        let mut player = Player::new(4.5, 4.5);
+        player.rotate(0.4);
+        let render = |culling: bool| {
+            let opts = RenderOptions { sprite_culling: culling, ..RenderOptions::default() };
+            let mut depth = DepthBuffer::new(w as usize);
+            let mut frame = vec![0u8; (w * h * 4) as usize];
+            render_scene(&mut frame, w, h, &level, &player, &pellets, &[], &mut depth, &opts);
+            frame
+        };
+        assert!(render(true) == render(false));
+    }
+
+    #[test]
+    fn pellet_stays_circular_at_16_9() {
+        let (w, h) = (640, 360);
+        let level = box_level();
+        let player = Player::new(3.5, 2.5); // mirando al oeste
+        let opts = RenderOptions { shadow_intensity: 0.0, ..RenderOptions::default() };
+        let mut depth = DepthBuffer::new(w as usize);
+        let pellet = [Sprite::new(1.5, 2.5, SpriteKind::Pellet)];
+
+        let mut empty = vec![0u8; (w * h * 4) as usize];
+        render_scene(&mut empty, w, h, &level, &player, &[], &[], &mut depth, &opts);
+        let mut frame = vec![0u8; (w * h * 4) as usize];
+        render_scene(&mut frame, w, h, &level, &player, &pellet, &[], &mut depth, &opts);
+
+        // Caja de los píxeles que cambió la moneda
+        let (mut x0, mut x1, mut y0, mut y1) = (w, -1, h, -1);
+        for y in 0..h {
+            for x in 0..w {
+                let idx = ((y * w + x) * 4) as usize;
+                if frame[idx..idx + 4] != empty[idx..idx + 4] {
+                    (x0, x1, y0, y1) = (x0.min(x), x1.max(x), y0.min(y), y1.max(y));
+                }
+            }
+        }
+        let (bw, bh) = (x1 - x0 + 1, y1 - y0 + 1);
+        assert!((bw - bh).abs() <= 2, "moneda deformada: {}x{}", bw, bh);
+
+        // Y con la misma escala que las posiciones horizontales: medio metro al
+        // costado a la distancia de la moneda se corre media unidad de pantalla
+        let (cx, _) = project_point(&player, w, 1.5, 2.5).unwrap();
+        let (sx, _) = project_point(&player, w, 1.5, 3.0).unwrap();
+        let px_per_unit = ((sx - cx) * 2).abs() as f32;
+        let expected = px_per_unit * sprite_scale(SpriteKind::Pellet);
+        assert!((bh as f32 - expected).abs() <= 3.0, "alto {} esperado {}", bh, expected);
+    }
+}
@@ -0,0 +1,526 @@
+// Opciones del juego ajustables desde el menú de opciones. Se guardan en
+// assets/settings.toml (solo pares clave = valor) cada vez que cambian.
+
+use anyhow::{bail, Context};
+
+use crate::palette::Palette;
+use crate::raycaster::MAX_VIEW_DISTANCE;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] = [Quality::Low, Quality::Medium, Quality::High];
+
+    // Cada cuántas columnas se lanza un rayo
+    pub fn column_step(self) -> i32 {
+        match self {
+            Quality::Low => 4,
+            Quality::Medium => 2,
+            Quality::High => 1,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Quality::Low => "Baja",
+            Quality::Medium => "Media",
+            Quality::High => "Alta",
+        }
+    }
+
+    fn cycle(self, delta: i32) -> Self {
+        let i = Self::ALL.iter().position(|q| *q == self).unwrap_or(0) as i32;
+        Self::ALL[(i + delta).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+}
+
+// Esquema de controles: "tanque" gira con Q/E; "moderno" usa A/D para
+// desplazarse de lado y deja el giro al mouse (las flechas siguen girando).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ControlScheme {
+    Tank,
+    Modern,
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 2] = [ControlScheme::Tank, ControlScheme::Modern];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ControlScheme::Tank => "Tanque",
+            ControlScheme::Modern => "Moderno",
+        }
+    }
+
+    fn toggle(self) -> Self {
+        match self {
+            ControlScheme::Tank => ControlScheme::Modern,
+            ControlScheme::Modern => ControlScheme::Tank,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    // Segundos de invulnerabilidad tras perder una vida
+    pub fn invuln_duration(self) -> f32 {
+        match self {
+            Difficulty::Easy => 3.5,
+            Difficulty::Normal => 2.0,
+            Difficulty::Hard => 1.0,
+        }
+    }
+
+    // Multiplicador de velocidad de los fantasmas durante la estampida final
+    pub fn rush_speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.2,
+            Difficulty::Normal => 1.4,
+            Difficulty::Hard => 1.7,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Facil",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Dificil",
+        }
+    }
+
+    fn cycle(self, delta: i32) -> Self {
+        let i = Self::ALL.iter().position(|d| *d == self).unwrap_or(0) as i32;
+        Self::ALL[(i + delta).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+}
+
+pub struct Settings {
+    pub quality: Quality,
+    pub shadow_intensity: f32,
+    pub spectator: bool, // cámara libre tras Game Over
+    pub timed_mode: bool, // contrarreloj: usa el límite de tiempo del nivel
+    pub minimap_rotate: bool, // minimapa girado con el jugador mirando hacia arriba
+    pub starting_lives: i32,
+    pub controls: ControlScheme,
+    pub supersample: bool, // antialiasing 2x: renderiza al doble y promedia (caro; solo en calidad Alta)
+    pub difficulty: Difficulty,
+    pub respawn_in_place: bool, // tras perder una vida, seguir donde se murió
+    pub palette: Palette,
+    pub practice: bool, // sin fantasmas ni derrota, para aprender el nivel
+    pub music_duck: f32, // cuánto baja la música con los SFX importantes (0..1)
+    pub room_highlight: bool, // minimapa: resaltar habitaciones con monedas pendientes
+    pub shake_strength: f32,  // vibración de cámara al recibir golpes (0 = apagada)
+    pub incremental_rays: bool, // rayos incrementales: algo más rápido en equipos lentos
+    pub grid_movement: bool,    // movimiento clásico de celda en celda
+    pub mouse_smoothing: f32,   // suavizado del mouse (0 = apagado, giro directo)
+    pub mouse_accel: bool,      // aceleración: los movimientos rápidos giran más
+    pub freeze_lethal: bool,    // los fantasmas congelados siguen quitando vidas al tocarlos
+    pub view_distance: f32,     // distancia de dibujo en celdas (con niebla al final)
+    pub sfx_echo: bool,         // eco en los efectos de sonido de los niveles grandes
+    pub depth_contrast: f32,    // paredes cercanas más claras y lejanas más oscuras (0 = apagado)
+    pub lean_distance: f32,     // cuánto se corre la cámara al asomarse, en celdas (0 = apagado)
+    pub rush_event: bool,       // estampida: con pocas monedas los fantasmas cargan un rato
+    pub hud_backing: bool,      // fondo oscuro detrás de los textos del HUD (más legible)
+    pub peek_walls: bool,       // ayuda: las paredes pegadas se ven translúcidas
+    pub minimap_memory: bool,   // minimapa: fantasmas solo donde se los vio por última vez
+    pub ghost_vision: bool,     // los fantasmas solo persiguen si ven al jugador de frente
+    pub crt_intensity: f32,     // efecto CRT: líneas de barrido y viñeta (0 = apagado)
+    pub breadcrumbs: bool,      // minimapa: rastro de las celdas pisadas hace poco
+    pub level_preview: bool,    // mostrar el mapa completo antes de empezar cada nivel
+    pub mouse_sensitivity: f32, // radianes de giro por unidad de movimiento del mouse
+    pub invert_mouse_y: bool,   // mirada vertical invertida: subir el mouse mira hacia abajo
+    pub gentle_bumps: bool,     // tocar un fantasma empuja y aturde; solo choques seguidos quitan vida
+}
+
+pub const MAX_LIVES: i32 = 9;
+
+// Límites de las opciones numéricas (mínimo, máximo): los usan tanto el menú
+// como la lectura del archivo, que recorta los valores fuera de rango
+const LIVES_RANGE: (i32, i32) = (1, MAX_LIVES);
+const SHADOW_RANGE: (f32, f32) = (0.0, 0.9);
+const MUSIC_DUCK_RANGE: (f32, f32) = (0.0, 1.0);
+const SHAKE_RANGE: (f32, f32) = (0.0, 2.0);
+const SMOOTHING_RANGE: (f32, f32) = (0.0, 0.9);
+const VIEW_DISTANCE_RANGE: (f32, f32) = (8.0, MAX_VIEW_DISTANCE);
+const CONTRAST_RANGE: (f32, f32) = (0.0, 1.0);
+const LEAN_RANGE: (f32, f32) = (0.0, 0.4);
+const CRT_RANGE: (f32, f32) = (0.0, 1.0);
+const SENSITIVITY_RANGE: (f32, f32) = (0.001, 0.01);
+
+pub const SETTINGS_PATH: &str = "assets/settings.toml";
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            quality: Quality::High,
+            shadow_intensity: 0.45,
+            spectator: false,
+            timed_mode: false,
+            minimap_rotate: false,
+            starting_lives: 3,
+            controls: ControlScheme::Tank,
+            supersample: false,
+            difficulty: Difficulty::Normal,
+            respawn_in_place: false,
+            palette: Palette::Standard,
+            practice: false,
+            music_duck: 0.6,
+            room_highlight: false,
+            shake_strength: 1.0,
+            incremental_rays: false,
+            grid_movement: false,
+            mouse_smoothing: 0.0,
+            mouse_accel: false,
+            freeze_lethal: false,
+            view_distance: MAX_VIEW_DISTANCE,
+            sfx_echo: false,
+            depth_contrast: 0.0,
+            lean_distance: 0.3,
+            rush_event: false,
+            hud_backing: false,
+            peek_walls: false,
+            minimap_memory: false,
+            ghost_vision: false,
+            crt_intensity: 0.0,
+            breadcrumbs: false,
+            level_preview: true,
+            mouse_sensitivity: 0.0035,
+            invert_mouse_y: false,
+            gentle_bumps: false,
+        }
+    }
+}
+
+impl Settings {
+    // Número de entradas del menú de opciones
+    pub const ITEM_COUNT: usize = 35;
+
+    pub fn item_label(&self, i: usize) -> String {
+        match i {
+            0 => format!("Calidad: {}", self.quality.label()),
+            1 => format!("Sombras: {:.0}", self.shadow_intensity * 100.0),
+            2 => format!("Espectador tras Game Over: {}", on_off(self.spectator)),
+            3 => format!("Contrarreloj: {}", on_off(self.timed_mode)),
+            4 => format!("Minimapa: {}", if self.minimap_rotate { "Rotativo" } else { "Norte arriba" }),
+            5 => format!("Vidas iniciales: {}", self.starting_lives),
+            6 => format!("Controles: {}", self.controls.label()),
+            7 => {
+                let inactive = self.supersample && !self.supersampling();
+                let note = if inactive { " - solo en calidad Alta" } else { "" };
+                format!("Antialiasing 2x: {}{}", on_off(self.supersample), note)
+            }
+            8 => format!("Dificultad: {}", self.difficulty.label()),
+            9 => format!("Reaparecer donde se murio: {}", on_off(self.respawn_in_place)),
+            10 => format!("Colores: {}", self.palette.label()),
+            11 => format!("Practica sin fantasmas: {}", on_off(self.practice)),
+            12 => format!("Bajar musica con efectos: {:.0}", self.music_duck * 100.0),
+            13 => format!("Resaltar habitaciones pendientes: {}", on_off(self.room_highlight)),
+            14 => format!("Vibracion de camara: {:.0}", self.shake_strength * 100.0),
+            15 => format!("Rayos incrementales: {}", on_off(self.incremental_rays)),
+            16 => format!("Movimiento en grilla: {}", on_off(self.grid_movement)),
+            17 => format!("Suavizado del mouse: {:.0}", self.mouse_smoothing * 100.0),
+            18 => format!("Aceleracion del mouse: {}", on_off(self.mouse_accel)),
+            19 => format!("Fantasmas congelados matan: {}", on_off(self.freeze_lethal)),
+            20 => format!("Distancia de dibujo: {:.0}", self.view_distance),
+            21 => format!("Eco en niveles grandes: {}", on_off(self.sfx_echo)),
+            22 => format!("Contraste por distancia: {:.0}", self.depth_contrast * 100.0),
+            23 => format!("Asomarse: {:.0}", self.lean_distance * 100.0),
+            24 => format!("Estampida final: {}", on_off(self.rush_event)),
+            25 => format!("Fondo en textos del HUD: {}", on_off(self.hud_backing)),
+            26 => format!("Ver a traves de paredes pegadas: {}", on_off(self.peek_walls)),
+            27 => format!("Minimapa con memoria: {}", on_off(self.minimap_memory)),
+            28 => format!("Fantasmas con vision limitada: {}", on_off(self.ghost_vision)),
+            29 => format!("Efecto CRT: {:.0}", self.crt_intensity * 100.0),
+            30 => format!("Rastro en el minimapa: {}", on_off(self.breadcrumbs)),
+            31 => format!("Vista previa del nivel: {}", on_off(self.level_preview)),
+            32 => format!("Sensibilidad del mouse: {:.1}", self.mouse_sensitivity * 1000.0),
+            33 => format!("Invertir eje Y del mouse: {}", on_off(self.invert_mouse_y)),
+            34 => format!("Choques suaves con fantasmas: {}", on_off(self.gentle_bumps)),
+            _ => String::new(),
+        }
+    }
+
+    // El antialiasing va junto con la calidad Alta: con menos calidad se saltean
+    // columnas y renderizar al doble no tendría sentido
+    pub fn supersampling(&self) -> bool {
+        self.supersample && self.quality == Quality::High
+    }
+
+    // delta = -1 / +1 según flecha izquierda / derecha
+    pub fn adjust(&mut self, i: usize, delta: i32) {
+        match i {
+            0 => self.quality = self.quality.cycle(delta),
+            1 => self.shadow_intensity = clamp_to(self.shadow_intensity + delta as f32 * 0.15, SHADOW_RANGE),
+            2 => self.spectator = !self.spectator,
+            3 => self.timed_mode = !self.timed_mode,
+            4 => self.minimap_rotate = !self.minimap_rotate,
+            5 => self.starting_lives = clamp_to(self.starting_lives + delta, LIVES_RANGE),
+            6 => self.controls = self.controls.toggle(),
+            7 => self.supersample = !self.supersample,
+            8 => self.difficulty = self.difficulty.cycle(delta),
+            9 => self.respawn_in_place = !self.respawn_in_place,
+            10 => self.palette = self.palette.toggle(),
+            11 => self.practice = !self.practice,
+            12 => self.music_duck = clamp_to(self.music_duck + delta as f32 * 0.2, MUSIC_DUCK_RANGE),
+            13 => self.room_highlight = !self.room_highlight,
+            14 => self.shake_strength = clamp_to(self.shake_strength + delta as f32 * 0.25, SHAKE_RANGE),
+            15 => self.incremental_rays = !self.incremental_rays,
+            16 => self.grid_movement = !self.grid_movement,
+            17 => self.mouse_smoothing = clamp_to(self.mouse_smoothing + delta as f32 * 0.1, SMOOTHING_RANGE),
+            18 => self.mouse_accel = !self.mouse_accel,
+            19 => self.freeze_lethal = !self.freeze_lethal,
+            20 => self.view_distance = clamp_to(self.view_distance + delta as f32 * 8.0, VIEW_DISTANCE_RANGE),
+            21 => self.sfx_echo = !self.sfx_echo,
+            22 => self.depth_contrast = clamp_to(self.depth_contrast + delta as f32 * 0.25, CONTRAST_RANGE),
+            23 => self.lean_distance = clamp_to(self.lean_distance + delta as f32 * 0.1, LEAN_RANGE),
+            24 => self.rush_event = !self.rush_event,
+            25 => self.hud_backing = !self.hud_backing,
+            26 => self.peek_walls = !self.peek_walls,
+            27 => self.minimap_memory = !self.minimap_memory,
+            28 => self.ghost_vision = !self.ghost_vision,
+            29 => self.crt_intensity = clamp_to(self.crt_intensity + delta as f32 * 0.25, CRT_RANGE),
+            30 => self.breadcrumbs = !self.breadcrumbs,
+            31 => self.level_preview = !self.level_preview,
+            32 => {
+                let sensitivity = self.mouse_sensitivity + delta as f32 * 0.0005;
+                self.mouse_sensitivity = clamp_to(sensitivity, SENSITIVITY_RANGE)
+            }
+            33 => self.invert_mouse_y = !self.invert_mouse_y,
+            34 => self.gentle_bumps = !self.gentle_bumps,
+            _ => {}
+        }
+    }
+}
+
+impl Settings {
+    // Sin archivo se usan las opciones por defecto; de un archivo con errores se
+    // aprovecha lo que se pueda leer, para no impedir que el juego arranque.
+    pub fn load(path: &str) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(_) => return Self::default(),
+        };
+        Self::from_toml(&text)
+    }
+
+    // Se escribe a un temporal y se renombra: si el juego se corta a mitad de
+    // camino queda el archivo anterior entero
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let tmp = format!("{}.tmp", path);
+        std::fs::write(&tmp, self.to_toml()).with_context(|| format!("No se pudo escribir {}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("No se pudo reemplazar {}", path))
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# Opciones del juego: se reescribe al cambiarlas desde el menú\n");
+        let mut put = |key: &str, value: String| out.push_str(&format!("{} = {}\n", key, value));
+        put("quality", quoted(self.quality.label()));
+        put("shadow_intensity", self.shadow_intensity.to_string());
+        put("spectator", self.spectator.to_string());
+        put("timed_mode", self.timed_mode.to_string());
+        put("minimap_rotate", self.minimap_rotate.to_string());
+        put("starting_lives", self.starting_lives.to_string());
+        put("controls", quoted(self.controls.label()));
+        put("supersample", self.supersample.to_string());
+        put("difficulty", quoted(self.difficulty.label()));
+        put("respawn_in_place", self.respawn_in_place.to_string());
+        put("palette", quoted(self.palette.label()));
+        put("practice", self.practice.to_string());
+        put("music_duck", self.music_duck.to_string());
+        put("room_highlight", self.room_highlight.to_string());
+        put("shake_strength", self.shake_strength.to_string());
+        put("incremental_rays", self.incremental_rays.to_string());
+        put("grid_movement", self.grid_movement.to_string());
+        put("mouse_smoothing", self.mouse_smoothing.to_string());
+        put("mouse_accel", self.mouse_accel.to_string());
+        put("freeze_lethal", self.freeze_lethal.to_string());
+        put("view_distance", self.view_distance.to_string());
+        put("sfx_echo", self.sfx_echo.to_string());
+        put("depth_contrast", self.depth_contrast.to_string());
+        put("lean_distance", self.lean_distance.to_string());
+        put("rush_event", self.rush_event.to_string());
+        put("hud_backing", self.hud_backing.to_string());
+        put("peek_walls", self.peek_walls.to_string());
+        put("minimap_memory", self.minimap_memory.to_string());
+        put("ghost_vision", self.ghost_vision.to_string());
+        put("crt_intensity", self.crt_intensity.to_string());
+        put("breadcrumbs", self.breadcrumbs.to_string());
+        put("level_preview", self.level_preview.to_string());
+        put("mouse_sensitivity", self.mouse_sensitivity.to_string());
+        put("invert_mouse_y", self.invert_mouse_y.to_string());
+        put("gentle_bumps", self.gentle_bumps.to_string());
+        out
+    }
+
+    // Las claves ausentes conservan el valor por defecto y los números fuera de
+    // rango se recortan a los mismos límites que el menú. Una línea inválida se
+    // avisa por consola y se saltea sin perder el resto de las opciones.
+    pub fn from_toml(text: &str) -> Self {
+        let mut s = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, v)) = line.split_once('=') else {
+                eprintln!("Aviso: opciones, línea {}: falta '='", n + 1);
+                continue;
+            };
+            let key = key.trim();
+            if let Err(e) = s.set_from_toml(key, v.trim()) {
+                eprintln!("Aviso: opciones, línea {}: {}: {:#}", n + 1, key, e);
+            }
+        }
+        s
+    }
+
+    fn set_from_toml(&mut self, key: &str, v: &str) -> anyhow::Result<()> {
+        match key {
+            "quality" => self.quality = parse_choice(v, &Quality::ALL, Quality::label)?,
+            "shadow_intensity" => self.shadow_intensity = clamp_to(parse_f32(v)?, SHADOW_RANGE),
+            "spectator" => self.spectator = parse_bool(v)?,
+            "timed_mode" => self.timed_mode = parse_bool(v)?,
+            "minimap_rotate" => self.minimap_rotate = parse_bool(v)?,
+            "starting_lives" => {
+                let lives: i32 = v.parse().context("se esperaba un entero")?;
+                self.starting_lives = clamp_to(lives, LIVES_RANGE);
+            }
+            "controls" => self.controls = parse_choice(v, &ControlScheme::ALL, ControlScheme::label)?,
+            "supersample" => self.supersample = parse_bool(v)?,
+            "difficulty" => self.difficulty = parse_choice(v, &Difficulty::ALL, Difficulty::label)?,
+            "respawn_in_place" => self.respawn_in_place = parse_bool(v)?,
+            "palette" => self.palette = parse_choice(v, &Palette::ALL, Palette::label)?,
+            "practice" => self.practice = parse_bool(v)?,
+            "music_duck" => self.music_duck = clamp_to(parse_f32(v)?, MUSIC_DUCK_RANGE),
+            "room_highlight" => self.room_highlight = parse_bool(v)?,
+            "shake_strength" => self.shake_strength = clamp_to(parse_f32(v)?, SHAKE_RANGE),
+            "incremental_rays" => self.incremental_rays = parse_bool(v)?,
+            "grid_movement" => self.grid_movement = parse_bool(v)?,
+            "mouse_smoothing" => self.mouse_smoothing = clamp_to(parse_f32(v)?, SMOOTHING_RANGE),
+            "mouse_accel" => self.mouse_accel = parse_bool(v)?,
+            "freeze_lethal" => self.freeze_lethal = parse_bool(v)?,
+            "view_distance" => self.view_distance = clamp_to(parse_f32(v)?, VIEW_DISTANCE_RANGE),
+            "sfx_echo" => self.sfx_echo = parse_bool(v)?,
+            "depth_contrast" => self.depth_contrast = clamp_to(parse_f32(v)?, CONTRAST_RANGE),
+            "lean_distance" => self.lean_distance = clamp_to(parse_f32(v)?, LEAN_RANGE),
+            "rush_event" => self.rush_event = parse_bool(v)?,
+            "hud_backing" => self.hud_backing = parse_bool(v)?,
+            "peek_walls" => self.peek_walls = parse_bool(v)?,
+            "minimap_memory" => self.minimap_memory = parse_bool(v)?,
+            "ghost_vision" => self.ghost_vision = parse_bool(v)?,
+            "crt_intensity" => self.crt_intensity = clamp_to(parse_f32(v)?, CRT_RANGE),
+            "breadcrumbs" => self.breadcrumbs = parse_bool(v)?,
+            "level_preview" => self.level_preview = parse_bool(v)?,
+            "mouse_sensitivity" => self.mouse_sensitivity = clamp_to(parse_f32(v)?, SENSITIVITY_RANGE),
+            "invert_mouse_y" => self.invert_mouse_y = parse_bool(v)?,
+            "gentle_bumps" => self.gentle_bumps = parse_bool(v)?,
+            _ => bail!("opción desconocida"),
+        }
+        Ok(())
+    }
+}
+
+fn clamp_to<T: PartialOrd>(v: T, (min, max): (T, T)) -> T {
+    if v < min {
+        min
+    } else if v > max {
+        max
+    } else {
+        v
+    }
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text)
+}
+
+fn parse_bool(v: &str) -> anyhow::Result<bool> {
+    match v {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!("se esperaba true o false"),
+    }
+}
+
+fn parse_f32(v: &str) -> anyhow::Result<f32> {
+    v.parse::<f32>().ok().filter(|x| x.is_finite()).context("se esperaba un número")
+}
+
+// Variante de un enum guardada por su etiqueta, entre comillas
+fn parse_choice<T: Copy>(v: &str, all: &[T], label: fn(T) -> &'static str) -> anyhow::Result<T> {
+    let name = v.strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+    let name = name.context("se esperaba un texto entre comillas")?;
+    all.iter().copied().find(|&c| label(c) == name).with_context(|| format!("valor desconocido \"{}\"", name))
+}
+
+fn on_off(v: bool) -> &'static str {
+    if v {
+        "Si"
+    } else {
+        "No"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_toml_round_trip() {
+        let s = Settings {
+            quality: Quality::Low,
+            difficulty: Difficulty::Hard,
+            controls: ControlScheme::Modern,
+            palette: Palette::ColorBlind,
+            shadow_intensity: 0.3,
+            starting_lives: 5,
+            breadcrumbs: true,
+            mouse_sensitivity: 0.005,
+            invert_mouse_y: true,
+            ..Settings::default()
+        };
+        let back = Settings::from_toml(&s.to_toml());
+        assert!(back.quality == Quality::Low && back.difficulty == Difficulty::Hard);
+        assert!(back.controls == ControlScheme::Modern && back.palette == Palette::ColorBlind);
+        assert_eq!(back.shadow_intensity, 0.3);
+        assert_eq!(back.starting_lives, 5);
+        assert!(back.breadcrumbs);
+        assert_eq!(back.mouse_sensitivity, 0.005);
+        assert!(back.invert_mouse_y);
+    }
+
+    #[test]
+    fn supersampling_only_runs_at_high_quality() {
+        let s = Settings { supersample: true, quality: Quality::High, ..Settings::default() };
+        assert!(s.supersampling());
+        let s = Settings { quality: Quality::Medium, ..s };
+        assert!(!s.supersampling());
+        assert!(s.item_label(7).ends_with("solo en calidad Alta"));
+    }
+
+    #[test]
+    fn settings_file_is_clamped_and_skips_bad_values() {
+        let s = Settings::from_toml("# comentario\nstarting_lives = 99\ncrt_intensity = -3\n");
+        assert_eq!(s.starting_lives, MAX_LIVES);
+        assert_eq!(s.crt_intensity, 0.0);
+        // Las líneas inválidas se saltean sin perder las demás
+        let text = "quality = \"Ultra\"\npractice = quizas\nsin igual\nbreadcrumbs = true\n";
+        let s = Settings::from_toml(text);
+        assert!(s.quality == Settings::default().quality);
+        assert_eq!(s.practice, Settings::default().practice);
+        assert!(s.breadcrumbs);
+    }
+}
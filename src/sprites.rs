@@ -1,6 +1,9 @@
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum SpriteKind {
     Pellet,
+    BigDot,      // moneda grande: vale más, pocas por nivel (en los callejones)
+    PowerPellet, // vuelve comestibles a los fantasmas por un rato
+    FreezePellet, // congela a todos los fantasmas en su lugar
     Ghost,
 }
 
@@ -8,16 +11,75 @@ pub struct Sprite {
     pub x: f32,
     pub y: f32,
     pub kind: SpriteKind,
-    pub anim_frame: usize,
-    pub anim_time: f32,
 }
 
 impl Sprite {
     pub fn new(x: f32, y: f32, kind: SpriteKind) -> Self {
+        Self { x, y, kind }
+    }
+}
+// Cuadro direccional del fantasma respecto a la cámara
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Facing {
+    Front,
+    Right,
+    Back,
+    Left,
+}
+
+// Nombres clásicos para la leyenda, en el orden de color_index
+pub const GHOST_NAMES: [&str; 4] = ["Blinky", "Pinky", "Inky", "Clyde"];
+
+// Estado del fantasma: persigue, huye (comestible) o vuelve a casa como ojos
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum GhostState {
+    Normal,
+    Frightened,
+    Eyes,
+}
+
+#[derive(Clone)]
+pub struct Ghost {
+    pub x: f32,
+    pub y: f32,
+    pub prev_x: f32, // posición del paso anterior (interpolación)
+    pub prev_y: f32,
+    pub heading: f32, // ángulo de movimiento (radianes)
+    pub vel_x: f32,   // velocidad actual (solo importa sobre hielo)
+    pub vel_y: f32,
+    pub anim_frame: usize,
+    pub anim_time: f32,
+    pub state: GhostState,
+    pub state_timer: f32, // restante de Frightened, o espera en casa como Eyes
+    pub home_x: f32,      // casa: donde apareció y a donde vuelve al ser comido
+    pub home_y: f32,
+    pub target_x: f32, // objetivo actual de la IA (depuración)
+    pub target_y: f32,
+    pub speed: f32, // velocidad relativa a la del grupo (1 = la de GhostTuning)
+    pub alert: f32, // visión limitada: segundos que sigue persiguiendo tras perder de vista al jugador
+    pub color_index: usize, // color propio (ver Theme::ghosts y GHOST_NAMES)
+}
+
+impl Ghost {
+    pub fn new(x: f32, y: f32) -> Self {
         Self {
-            x, y, kind,
+            x, y,
+            prev_x: x,
+            prev_y: y,
+            heading: 0.0,
+            vel_x: 0.0,
+            vel_y: 0.0,
             anim_frame: 0,
             anim_time: 0.0,
+            state: GhostState::Normal,
+            state_timer: 0.0,
+            home_x: x,
+            home_y: y,
+            target_x: x,
+            target_y: y,
+            speed: 1.0,
+            alert: 0.0,
+            color_index: 0,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,218 @@
+// Tema visual cargado desde JSON (assets/theme.json): paredes, cielo/piso,
+// HUD y sprites. Cualquier clave ausente conserva el color por defecto y los
+// valores fuera de rango se recortan a 0..255.
+
+use anyhow::{bail, Context};
+use serde_json::Value;
+
+pub type Rgb = [u8; 3];
+
+pub const THEME_PATH: &str = "assets/theme.json";
+
+#[derive(Copy, Clone)]
+pub struct Theme {
+    pub walls: [Rgb; 6],
+    // Si se definen, reemplazan el cielo propio de cada nivel
+    pub sky_top: Option<Rgb>,
+    pub sky_horizon: Option<Rgb>,
+    pub floor_horizon: Rgb,
+    pub floor_bottom: Rgb,
+    pub hud_text: Rgb,
+    pub hud_dim: Rgb,
+    pub hud_danger: Rgb, // vidas, poco tiempo y fantasmas en el minimapa
+    pub player_marker: Rgb,
+    pub pellet: Rgb,
+    pub big_dot: Rgb,
+    pub power_pellet: Rgb,
+    pub ghosts: [Rgb; 4], // un color por fantasma (según color_index)
+    pub frightened: Rgb,
+    pub frozen: Rgb, // cristal de congelamiento y tinte de los fantasmas congelados
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            walls: [
+                [200, 60, 60],
+                [60, 200, 60],
+                [60, 60, 200],
+                [200, 200, 60],
+                [200, 60, 200],
+                [60, 200, 200],
+            ],
+            sky_top: None,
+            sky_horizon: None,
+            floor_horizon: [48, 48, 48],
+            floor_bottom: [24, 24, 24],
+            hud_text: [255, 255, 255],
+            hud_dim: [200, 200, 200],
+            hud_danger: [255, 80, 80],
+            player_marker: [255, 255, 0],
+            pellet: [255, 230, 0],
+            big_dot: [255, 150, 40],
+            power_pellet: [255, 190, 230],
+            ghosts: [[255, 60, 60], [255, 150, 220], [60, 220, 255], [255, 170, 60]],
+            frightened: [50, 70, 230],
+            frozen: [160, 230, 255],
+        }
+    }
+}
+
+impl Theme {
+    // Paleta apta para daltonismo (protanopía/deuteranopía): paredes en
+    // azules/amarillos/grises, fantasmas en naranja y jugador en blanco.
+    pub fn color_blind() -> Self {
+        Self {
+            walls: [
+                [0, 114, 178],
+                [230, 159, 0],
+                [86, 180, 233],
+                [240, 228, 66],
+                [204, 121, 167],
+                [170, 170, 170],
+            ],
+            hud_danger: [230, 120, 0],
+            player_marker: [255, 255, 255],
+            pellet: [240, 228, 66],
+            ghosts: [[213, 94, 0], [204, 121, 167], [0, 158, 115], [230, 159, 0]],
+            ..Self::default()
+        }
+    }
+
+    // Sin archivo se usa el tema por defecto; un archivo inválido también,
+    // avisando por consola para no impedir que el juego arranque.
+    pub fn load(path: &str) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(_) => return Self::default(),
+        };
+        match Self::from_json(&text) {
+            Ok(theme) => theme,
+            Err(e) => {
+                eprintln!("Aviso: tema {} ignorado: {:#}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn from_json(text: &str) -> anyhow::Result<Self> {
+        let root: Value = serde_json::from_str(text).context("JSON inválido")?;
+        let Some(obj) = root.as_object() else {
+            bail!("se esperaba un objeto en la raíz");
+        };
+
+        let mut t = Self::default();
+        for (key, v) in obj {
+            match key.as_str() {
+                "walls" => {
+                    let list = v.as_array().context("walls debe ser una lista de colores")?;
+                    for (slot, c) in t.walls.iter_mut().zip(list) {
+                        *slot = parse_rgb(c).context("walls")?;
+                    }
+                }
+                "sky_top" => t.sky_top = Some(parse_rgb(v).context("sky_top")?),
+                "sky_horizon" => t.sky_horizon = Some(parse_rgb(v).context("sky_horizon")?),
+                "floor_horizon" => t.floor_horizon = parse_rgb(v).context("floor_horizon")?,
+                "floor_bottom" => t.floor_bottom = parse_rgb(v).context("floor_bottom")?,
+                "hud_text" => t.hud_text = parse_rgb(v).context("hud_text")?,
+                "hud_dim" => t.hud_dim = parse_rgb(v).context("hud_dim")?,
+                "hud_danger" => t.hud_danger = parse_rgb(v).context("hud_danger")?,
+                "player_marker" => t.player_marker = parse_rgb(v).context("player_marker")?,
+                "pellet" => t.pellet = parse_rgb(v).context("pellet")?,
+                "big_dot" => t.big_dot = parse_rgb(v).context("big_dot")?,
+                "power_pellet" => t.power_pellet = parse_rgb(v).context("power_pellet")?,
+                "ghosts" => {
+                    let list = v.as_array().context("ghosts debe ser una lista de colores")?;
+                    for (slot, c) in t.ghosts.iter_mut().zip(list) {
+                        *slot = parse_rgb(c).context("ghosts")?;
+                    }
+                }
+                // Clave de temas viejos: un color por cuadro de animación. El primero
+                // pasa a todos los fantasmas, salvo que el tema ya traiga "ghosts"
+                "ghost" if !obj.contains_key("ghosts") => {
+                    let list = v.as_array().context("ghost debe ser una lista de colores")?;
+                    let first = list.first().context("ghost está vacía")?;
+                    t.ghosts = [parse_rgb(first).context("ghost")?; 4];
+                }
+                "ghost" => {}
+                "frightened" => t.frightened = parse_rgb(v).context("frightened")?,
+                "frozen" => t.frozen = parse_rgb(v).context("frozen")?,
+                other => eprintln!("Aviso: clave de tema desconocida: {}", other),
+            }
+        }
+        Ok(t)
+    }
+}
+
+// [r, g, b] con números cualesquiera, recortados a 0..255
+fn parse_rgb(v: &Value) -> anyhow::Result<Rgb> {
+    let arr = v.as_array().filter(|a| a.len() == 3).context("color debe ser [r, g, b]")?;
+    let mut out = [0u8; 3];
+    for (o, c) in out.iter_mut().zip(arr) {
+        let n = c.as_f64().context("componente de color no numérica")?;
+        *o = n.round().clamp(0.0, 255.0) as u8;
+    }
+    Ok(out)
+}
+
+pub fn rgba(c: Rgb, a: u8) -> [u8; 4] {
+    [c[0], c[1], c[2], a]
+}
+
+// Color del fantasma; el segundo cuadro de animación es un poco más claro
+pub fn ghost_color(theme: &Theme, color_index: usize, anim_frame: usize, alpha: u8) -> [u8; 4] {
+    let c = theme.ghosts[color_index % theme.ghosts.len()];
+    if anim_frame.is_multiple_of(2) {
+        rgba(c, alpha)
+    } else {
+        let lighten = |v: u8| v + (255 - v) / 5;
+        [lighten(c[0]), lighten(c[1]), lighten(c[2]), alpha]
+    }
+}
+
+pub fn wall_color(id: i32, theme: &Theme) -> [u8; 4] {
+    rgba(theme.walls[id.rem_euclid(6) as usize], 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_ghost_key_is_a_fallback_for_ghosts() {
+        let t = Theme::from_json(r#"{"ghost": [[10, 20, 30], [40, 50, 60]]}"#).expect("tema válido");
+        assert_eq!(t.ghosts, [[10, 20, 30]; 4]);
+        let t = Theme::from_json(r#"{"ghosts": [[1, 2, 3]], "ghost": [[9, 9, 9]]}"#).expect("tema válido");
+        assert_eq!(t.ghosts[0], [1, 2, 3]);
+        assert_eq!(t.ghosts[1], Theme::default().ghosts[1]);
+    }
+
+    #[test]
+    fn out_of_range_components_are_clamped() {
+        let t = Theme::from_json(r#"{"pellet": [300, -5, 12.6]}"#).expect("tema válido");
+        assert_eq!(t.pellet, [255, 0, 13]);
+    }
+
+    #[test]
+    fn missing_keys_keep_their_defaults() {
+        let d = Theme::default();
+        let t = Theme::from_json(r#"{"hud_text": [1, 2, 3], "walls": [[4, 5, 6]]}"#).expect("tema válido");
+        assert_eq!(t.hud_text, [1, 2, 3]);
+        assert_eq!(t.walls[0], [4, 5, 6]);
+        assert_eq!(t.walls[1..], d.walls[1..]);
+        assert_eq!(t.sky_top, d.sky_top);
+        assert_eq!(t.floor_bottom, d.floor_bottom);
+        assert_eq!(t.hud_danger, d.hud_danger);
+        assert_eq!(t.pellet, d.pellet);
+        assert_eq!(t.ghosts, d.ghosts);
+        assert_eq!(t.frozen, d.frozen);
+    }
+
+    #[test]
+    fn malformed_json_is_an_error() {
+        assert!(Theme::from_json("{\"pellet\": [1, 2, 3]").is_err());
+        assert!(Theme::from_json("[1, 2, 3]").is_err());
+        assert!(Theme::from_json(r#"{"pellet": [1, 2]}"#).is_err());
+        assert!(Theme::from_json(r#"{"hud_text": "rojo"}"#).is_err());
+    }
+}